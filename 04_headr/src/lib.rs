@@ -10,6 +10,22 @@ pub struct Config {
     files: Vec<String>,
     lines: usize,
     bytes: Option<usize>,
+    ranges: Option<Vec<LineRange>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct LineRange {
+    start: usize,
+    end: Option<usize>,
+    step: usize,
+}
+
+impl LineRange {
+    fn contains(&self, line_number: usize) -> bool {
+        line_number >= self.start
+            && self.end.is_none_or(|end| line_number <= end)
+            && (line_number - self.start).is_multiple_of(self.step)
+    }
 }
 
 pub fn run(config: Config) -> MyResult<()> {
@@ -31,6 +47,8 @@ pub fn run(config: Config) -> MyResult<()> {
                     let mut buffer = vec![0; number_of_bytes];
                     let bytes_read = handle.read(&mut buffer)?;
                     print!("{}", String::from_utf8_lossy(&buffer[..bytes_read]));
+                } else if let Some(ranges) = &config.ranges {
+                    print_ranges(file, ranges)?;
                 } else {
                     for _ in 0..config.lines {
                         let mut line: String = String::new();
@@ -47,6 +65,32 @@ pub fn run(config: Config) -> MyResult<()> {
     Ok(())
 }
 
+fn print_ranges(mut file: impl BufRead, ranges: &[LineRange]) -> MyResult<()> {
+    // Stop reading once every range is behind us, unless one runs to the end
+    let last_line = ranges
+        .iter()
+        .map(|range| range.end)
+        .collect::<Option<Vec<_>>>()
+        .and_then(|ends| ends.into_iter().max());
+
+    let mut line = String::new();
+    let mut line_number = 0;
+    loop {
+        line_number += 1;
+        if last_line.is_some_and(|last| line_number > last) {
+            break;
+        }
+        line.clear();
+        if file.read_line(&mut line)? == 0 {
+            break;
+        }
+        if ranges.iter().any(|range| range.contains(line_number)) {
+            print!("{}", line);
+        }
+    }
+    Ok(())
+}
+
 pub fn get_args() -> MyResult<Config> {
     let matches = App::new("headr")
         .version("0.1.0")
@@ -79,6 +123,14 @@ pub fn get_args() -> MyResult<Config> {
                 .conflicts_with("lines")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("range")
+                .value_name("RANGES")
+                .help("Print lines in comma separated START:END[:STEP] ranges")
+                .long("range")
+                .conflicts_with_all(&["lines", "bytes"])
+                .takes_value(true),
+        )
         .get_matches();
 
     let lines = matches
@@ -93,10 +145,17 @@ pub fn get_args() -> MyResult<Config> {
         .transpose()
         .map_err(|e| format!("illegal byte count -- {}", e))?;
 
+    let ranges = matches
+        .value_of("range")
+        .map(parse_ranges)
+        .transpose()
+        .map_err(|e| format!("illegal range -- {}", e))?;
+
     Ok(Config {
         files: matches.values_of_lossy("files").unwrap(),
         lines: lines.unwrap(),
         bytes,
+        ranges,
     })
 }
 
@@ -125,6 +184,90 @@ fn test_parse_positive_int() {
     assert_eq!(res.unwrap_err().to_string(), "0".to_string());
 }
 
+fn parse_ranges(value: &str) -> MyResult<Vec<LineRange>> {
+    value.split(',').map(parse_range).collect()
+}
+
+fn parse_range(value: &str) -> MyResult<LineRange> {
+    let parts: Vec<_> = value.split(':').collect();
+    let (start, end, step) = match parts.as_slice() {
+        [start] => (start, Some(start), None),
+        [start, end] => (start, Some(end), None),
+        [start, end, step] => (start, Some(end), Some(step)),
+        _ => return Err(value.into()),
+    };
+    let start = parse_positive_int(start).map_err(|_| value)?;
+    let end = end
+        .filter(|end| !end.is_empty())
+        .map(|end| parse_positive_int(end))
+        .transpose()
+        .map_err(|_| value)?;
+    let step = step
+        .map(|step| parse_positive_int(step))
+        .transpose()
+        .map_err(|_| value)?
+        .unwrap_or(1);
+    match end {
+        Some(end) if end < start => Err(value.into()),
+        _ => Ok(LineRange { start, end, step }),
+    }
+}
+
+#[test]
+fn test_parse_ranges() {
+    // single line
+    let res = parse_ranges("3");
+    assert_eq!(
+        res.unwrap(),
+        vec![LineRange {
+            start: 3,
+            end: Some(3),
+            step: 1
+        }]
+    );
+
+    // start and end, open end and step
+    let res = parse_ranges("1:5,100:,2:10:3");
+    assert_eq!(
+        res.unwrap(),
+        vec![
+            LineRange {
+                start: 1,
+                end: Some(5),
+                step: 1
+            },
+            LineRange {
+                start: 100,
+                end: None,
+                step: 1
+            },
+            LineRange {
+                start: 2,
+                end: Some(10),
+                step: 3
+            },
+        ]
+    );
+
+    // the offending range is reported
+    for bad in ["0:3", "5:2", "1:2:0", "a:b", "1:2:3:4", ""] {
+        let res = parse_ranges(&format!("1:2,{}", bad));
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), bad.to_string());
+    }
+}
+
+#[test]
+fn test_line_range_contains() {
+    let range = LineRange {
+        start: 2,
+        end: Some(10),
+        step: 3,
+    };
+    let selected: Vec<_> = (1..=12).filter(|&n| range.contains(n)).collect();
+    assert_eq!(selected, vec![2, 5, 8]);
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
@@ -41,7 +41,7 @@ fn dies_bad_bytes() -> TestResult {
     let bad = random_string();
     let expected = format!("illegal byte count -- {}", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-c", &bad, EMPTY])
+        .args(["-c", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -55,7 +55,7 @@ fn dies_bad_lines() -> TestResult {
     let bad = random_string();
     let expected = format!("illegal line count -- {}", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-n", &bad, EMPTY])
+        .args(["-n", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -70,7 +70,37 @@ fn dies_bytes_and_lines() -> TestResult {
                used with '--bytes <BYTES>'";
 
     Command::cargo_bin(PRG)?
-        .args(&["-n", "1", "-c", "2"])
+        .args(["-n", "1", "-c", "2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(msg));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_range() -> TestResult {
+    for bad in ["0:3", "5:2", "1:2:0", "a"] {
+        let expected = format!("illegal range -- {}", bad);
+        Command::cargo_bin(PRG)?
+            .args(["--range", &format!("1:2,{}", bad), EMPTY])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_range_and_bytes() -> TestResult {
+    let msg = "The argument '--bytes <BYTES>' cannot be \
+               used with '--range <RANGES>'";
+
+    Command::cargo_bin(PRG)?
+        .args(["--range", "1:2", "-c", "2"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(msg));
@@ -103,7 +133,7 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
         .args(args)
         .assert()
         .success()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(expected.as_bytes()));
 
     Ok(())
 }
@@ -125,7 +155,7 @@ fn run_stdin(
         .write_stdin(input)
         .args(args)
         .assert()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(expected.as_bytes()));
 
     Ok(())
 }
@@ -349,6 +379,27 @@ fn ten_c4() -> TestResult {
     run(&[TEN, "-c", "4"], "tests/expected/ten.txt.c4.out")
 }
 
+#[test]
+fn ten_range() -> TestResult {
+    run(&[TEN, "--range", "3:5"], "tests/expected/ten.txt.r3-5.out")
+}
+
+#[test]
+fn ten_range_open_end() -> TestResult {
+    run(
+        &[TEN, "--range", "1:2,8:"],
+        "tests/expected/ten.txt.r1-2_8-.out",
+    )
+}
+
+#[test]
+fn ten_range_step() -> TestResult {
+    run(
+        &[TEN, "--range", "1:10:3,2"],
+        "tests/expected/ten.txt.r1-10-3_2.out",
+    )
+}
+
 #[test]
 fn ten_stdin() -> TestResult {
     run_stdin(&[], TEN, "tests/expected/ten.txt.out")
@@ -374,6 +425,11 @@ fn ten_c4_stdin() -> TestResult {
     run_stdin(&["-c", "4"], TEN, "tests/expected/ten.txt.c4.out")
 }
 
+#[test]
+fn ten_range_stdin() -> TestResult {
+    run_stdin(&["--range", "3:5"], TEN, "tests/expected/ten.txt.r3-5.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn multiple_files() -> TestResult {
@@ -419,3 +475,11 @@ fn multiple_files_c4() -> TestResult {
         "tests/expected/all.c4.out",
     )
 }
+
+#[test]
+fn multiple_files_range() -> TestResult {
+    run(
+        &[EMPTY, ONE, TWO, THREE, TEN, "--range", "2:3"],
+        "tests/expected/all.r2-3.out",
+    )
+}
@@ -0,0 +1,28 @@
+#!/usr/bin/env bash
+
+# Times wcr against the system wc on files made by util/biggie
+# Usage: ./bench.sh [LINES] [FILES]
+
+set -e
+
+LINES=${1:-2000000}
+NUM_FILES=${2:-4}
+BIGGIE="../book_repo/util/biggie"
+OUT_DIR=$(mktemp -d)
+trap 'rm -rf "$OUT_DIR"' EXIT
+
+cargo build --release --quiet
+(cd "$BIGGIE" && cargo build --release --quiet)
+
+FILES=""
+for i in $(seq 1 "$NUM_FILES"); do
+    "$BIGGIE/target/release/biggie" -n "$LINES" -o "$OUT_DIR/$i.txt"
+    FILES="$FILES $OUT_DIR/$i.txt"
+done
+
+for FLAGS in "" -c -l -w -m -L; do
+    echo "==> wc $FLAGS <=="
+    time wc $FLAGS $FILES > /dev/null
+    echo "==> wcr $FLAGS <=="
+    time target/release/wcr $FLAGS $FILES > /dev/null
+done
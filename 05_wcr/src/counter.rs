@@ -0,0 +1,354 @@
+use crate::stats::TextStats;
+use crate::{FileInfo, MyResult};
+use memchr::memchr_iter;
+use std::io::BufRead;
+use std::str;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+// Which of the counts that need more than a byte scan should be made
+#[derive(Debug, Clone, Copy)]
+pub struct Selection {
+    pub words: bool,
+    pub chars: bool,
+    pub graphemes: bool,
+    pub unicode_words: bool,
+    pub max_line_length: bool,
+    pub stats: bool,
+}
+
+impl Selection {
+    // every column, but not the --stats report
+    pub fn all() -> Selection {
+        Selection {
+            words: true,
+            chars: true,
+            graphemes: true,
+            unicode_words: true,
+            max_line_length: true,
+            stats: false,
+        }
+    }
+}
+
+// Counts a stream chunk by chunk without building lines. Words and
+// line widths are tracked by a state machine that carries over chunk
+// boundaries, including a multibyte char split between two chunks.
+// Unicode segmentation is done a line at a time instead, as neither
+// grapheme clusters nor UAX #29 words reach past a line feed, and so
+// are the --stats metrics.
+pub struct Counter {
+    selection: Selection,
+    info: FileInfo,
+    in_word: bool,
+    position: usize,
+    pending: [u8; 4],
+    pending_len: usize,
+    line: Vec<u8>,
+}
+
+impl Counter {
+    pub fn new(selection: Selection) -> Counter {
+        Counter {
+            selection,
+            info: FileInfo {
+                stats: selection.stats.then(TextStats::default),
+                ..FileInfo::default()
+            },
+            in_word: false,
+            position: 0,
+            pending: [0; 4],
+            pending_len: 0,
+            line: Vec::new(),
+        }
+    }
+
+    pub fn count(mut self, mut file: impl BufRead) -> MyResult<FileInfo> {
+        loop {
+            let chunk = file.fill_buf()?;
+            if chunk.is_empty() {
+                break;
+            }
+            self.update(chunk);
+            let chunk_len = chunk.len();
+            file.consume(chunk_len);
+        }
+        Ok(self.finish())
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.info.bytes += chunk.len();
+        self.info.lines += memchr_iter(b'\n', chunk).count();
+        if self.selection.chars {
+            // every byte but a UTF-8 continuation byte starts a char
+            self.info.chars += chunk.iter().filter(|&&byte| (byte as i8) >= -0x40).count();
+        }
+        if self.selection.words || self.selection.max_line_length {
+            let rest = self.complete_pending(chunk);
+            self.scan(rest);
+        }
+        if self.selection.graphemes || self.selection.unicode_words || self.selection.stats {
+            self.segment_lines(chunk);
+        }
+    }
+
+    pub fn finish(mut self) -> FileInfo {
+        if self.pending_len > 0 {
+            self.push_invalid();
+        }
+        if !self.line.is_empty() {
+            let line = std::mem::take(&mut self.line);
+            self.segment(&line);
+        }
+        if let Some(stats) = &mut self.info.stats {
+            stats.finish();
+        }
+        self.info.max_line_length = self.info.max_line_length.max(self.position);
+        self.info
+    }
+
+    // Finish a char left over from the previous chunk, returning the
+    // part of the chunk that still needs scanning
+    fn complete_pending<'a>(&mut self, chunk: &'a [u8]) -> &'a [u8] {
+        if self.pending_len == 0 {
+            return chunk;
+        }
+        let char_len = match self.pending[0] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            _ => 4,
+        };
+        let mut used = 0;
+        while self.pending_len < char_len && used < chunk.len() && chunk[used] & 0xc0 == 0x80 {
+            self.pending[self.pending_len] = chunk[used];
+            self.pending_len += 1;
+            used += 1;
+        }
+        if self.pending_len == char_len {
+            let pending = self.pending;
+            match str::from_utf8(&pending[..char_len]) {
+                Ok(text) => self.scan_text(text),
+                Err(_) => self.push_invalid(),
+            }
+            self.pending_len = 0;
+        } else if used < chunk.len() {
+            // cut short by a byte that can't continue the char
+            self.push_invalid();
+            self.pending_len = 0;
+        }
+        &chunk[used..]
+    }
+
+    fn scan(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            match str::from_utf8(bytes) {
+                Ok(text) => {
+                    self.scan_text(text);
+                    break;
+                }
+                Err(err) => {
+                    let (valid, rest) = bytes.split_at(err.valid_up_to());
+                    // valid_up_to guarantees this part is UTF-8
+                    self.scan_text(str::from_utf8(valid).unwrap_or_default());
+                    match err.error_len() {
+                        Some(invalid_len) => {
+                            self.push_invalid();
+                            bytes = &rest[invalid_len..];
+                        }
+                        None => {
+                            // the chunk ends in the middle of a char
+                            self.pending[..rest.len()].copy_from_slice(rest);
+                            self.pending_len = rest.len();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn scan_text(&mut self, text: &str) {
+        if self.selection.words {
+            self.scan_words(text);
+        }
+        if self.selection.max_line_length {
+            self.scan_widths(text);
+        }
+    }
+
+    // The loops below keep their state in locals and only decode
+    // non-ASCII chars, which keeps them fast on mostly ASCII text
+    fn scan_words(&mut self, text: &str) {
+        let bytes = text.as_bytes();
+        let mut words = 0;
+        let mut in_word = self.in_word;
+        let mut index = 0;
+        while index < bytes.len() {
+            let is_space = match bytes[index] {
+                byte if byte.is_ascii() => {
+                    index += 1;
+                    matches!(byte, b' ' | b'\t'..=b'\r')
+                }
+                _ => {
+                    let c = text[index..].chars().next().unwrap_or_default();
+                    index += c.len_utf8();
+                    c.is_whitespace()
+                }
+            };
+            words += (!is_space && !in_word) as usize;
+            in_word = !is_space;
+        }
+        self.info.words += words;
+        self.in_word = in_word;
+    }
+
+    // Width follows GNU wc: tabs advance to the next multiple of 8,
+    // carriage returns and form feeds start over, wide chars count 2
+    // and combining marks and other control chars count 0
+    fn scan_widths(&mut self, text: &str) {
+        let bytes = text.as_bytes();
+        let mut widest = self.info.max_line_length;
+        let mut position = self.position;
+        let mut index = 0;
+        while index < bytes.len() {
+            match bytes[index] {
+                b'\t' => position += 8 - position % 8,
+                b'\n' | b'\r' | b'\x0c' => {
+                    widest = widest.max(position);
+                    position = 0;
+                }
+                b' '..=b'~' => position += 1,
+                byte if byte.is_ascii() => {}
+                _ => {
+                    let c = text[index..].chars().next().unwrap_or_default();
+                    position += c.width().unwrap_or(0);
+                    index += c.len_utf8();
+                    continue;
+                }
+            }
+            index += 1;
+        }
+        self.info.max_line_length = widest;
+        self.position = position;
+    }
+
+    fn segment_lines(&mut self, chunk: &[u8]) {
+        let mut start = 0;
+        for end in memchr_iter(b'\n', chunk) {
+            let line = &chunk[start..=end];
+            if self.line.is_empty() {
+                self.segment(line);
+            } else {
+                // the line began in an earlier chunk
+                let mut whole = std::mem::take(&mut self.line);
+                whole.extend_from_slice(line);
+                self.segment(&whole);
+            }
+            start = end + 1;
+        }
+        self.line.extend_from_slice(&chunk[start..]);
+    }
+
+    // Bytes that aren't UTF-8 count as one replacement char each
+    fn segment(&mut self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        if self.selection.graphemes {
+            self.info.graphemes += line.graphemes(true).count();
+        }
+        if self.selection.unicode_words {
+            self.info.unicode_words += line.unicode_words().count();
+        }
+        if let Some(stats) = &mut self.info.stats {
+            stats.push_line(&line);
+        }
+    }
+
+    // Bytes that aren't UTF-8 are part of a word but take no room
+    fn push_invalid(&mut self) {
+        if self.selection.words && !self.in_word {
+            self.info.words += 1;
+            self.in_word = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Counter, Selection};
+    use crate::FileInfo;
+
+    fn count_chunks(chunks: &[&[u8]]) -> FileInfo {
+        let mut counter = Counter::new(Selection::all());
+        for chunk in chunks {
+            counter.update(chunk);
+        }
+        counter.finish()
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        let text = "Frétt hefir\töld óvu, 日本語\r\nþá er  endr\n".as_bytes();
+        let whole = count_chunks(&[text]);
+        assert_eq!(
+            whole,
+            FileInfo {
+                lines: 2,
+                words: 8,
+                bytes: 49,
+                chars: 38,
+                graphemes: 37,
+                unicode_words: 10,
+                max_line_length: 31,
+                stats: None,
+            }
+        );
+
+        // splitting anywhere, even inside a char, gives the same counts
+        for at in 0..text.len() {
+            let (first, second) = text.split_at(at);
+            assert_eq!(count_chunks(&[first, second]), whole, "split at {}", at);
+        }
+        let bytes: Vec<&[u8]> = text.chunks(1).collect();
+        assert_eq!(count_chunks(&bytes), whole);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        // a stray continuation byte and a truncated char are word bytes
+        let info = count_chunks(&[b"a \x80 b \xe6\x97", b" c \xe6"]);
+        assert_eq!(info.words, 6);
+        assert_eq!(info.bytes, 12);
+        assert_eq!(info.chars, 10);
+
+        // a char cut short by the next chunk is not glued to it
+        let info = count_chunks(&[b"\xe6\x97", b"x"]);
+        assert_eq!(info.words, 1);
+        assert_eq!(info.max_line_length, 1);
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut counter = Counter::new(Selection {
+            words: false,
+            chars: false,
+            graphemes: false,
+            unicode_words: false,
+            max_line_length: false,
+            stats: false,
+        });
+        counter.update(b"one two\nthree\n");
+        assert_eq!(
+            counter.finish(),
+            FileInfo {
+                lines: 2,
+                words: 0,
+                bytes: 14,
+                chars: 0,
+                graphemes: 0,
+                unicode_words: 0,
+                max_line_length: 0,
+                stats: None,
+            }
+        );
+    }
+}
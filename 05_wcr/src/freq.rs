@@ -0,0 +1,176 @@
+use crate::MyResult;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::BufRead;
+
+#[derive(Debug)]
+pub struct FreqConfig {
+    pub fold_case: bool,
+    pub strip_punctuation: bool,
+    pub min_length: usize,
+    pub top: Option<usize>,
+    pub format: FreqFormat,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FreqFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+// Adds the words of a file to the tally, splitting on whitespace the
+// same way the word count does
+pub fn tally(
+    mut file: impl BufRead,
+    config: &FreqConfig,
+    tally: &mut HashMap<String, usize>,
+) -> MyResult<()> {
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if file.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        for word in String::from_utf8_lossy(&line).split_whitespace() {
+            if let Some(word) = normalize(word, config) {
+                *tally.entry(word).or_default() += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn normalize(word: &str, config: &FreqConfig) -> Option<String> {
+    // punctuation inside a word, as in "don't", is kept
+    let word = if config.strip_punctuation {
+        word.trim_matches(|c: char| !c.is_alphanumeric())
+    } else {
+        word
+    };
+    let word = if config.fold_case {
+        word.to_lowercase()
+    } else {
+        word.to_string()
+    };
+    if word.is_empty() || word.chars().count() < config.min_length {
+        None
+    } else {
+        Some(word)
+    }
+}
+
+// Most frequent first, ties in alphabetical order
+pub fn sort_tally(tally: HashMap<String, usize>, top: Option<usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<_> = tally.into_iter().collect();
+    counts.sort_by(|(word_a, count_a), (word_b, count_b)| {
+        count_b.cmp(count_a).then_with(|| word_a.cmp(word_b))
+    });
+    if let Some(top) = top {
+        counts.truncate(top);
+    }
+    counts
+}
+
+pub fn format_counts(counts: &[(String, usize)], format: &FreqFormat) -> String {
+    match format {
+        FreqFormat::Text => counts
+            .iter()
+            .map(|(word, count)| format!("{count:>7} {word}\n"))
+            .collect(),
+        FreqFormat::Csv => {
+            let mut out = "word,count\n".to_string();
+            for (word, count) in counts {
+                out += &format!("{},{}\n", csv_field(word), count);
+            }
+            out
+        }
+        FreqFormat::Json => {
+            let counts: Vec<_> = counts
+                .iter()
+                .map(|(word, count)| json!({"word": word, "count": count}))
+                .collect();
+            format!("{}\n", json!(counts))
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_counts, normalize, sort_tally, tally, FreqConfig, FreqFormat};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    fn config() -> FreqConfig {
+        FreqConfig {
+            fold_case: false,
+            strip_punctuation: false,
+            min_length: 0,
+            top: None,
+            format: FreqFormat::Text,
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let plain = config();
+        assert_eq!(normalize("\"Don't!\"", &plain), Some("\"Don't!\"".into()));
+
+        let config = FreqConfig {
+            fold_case: true,
+            strip_punctuation: true,
+            min_length: 3,
+            ..config()
+        };
+        assert_eq!(normalize("\"Don't!\"", &config), Some("don't".into()));
+        assert_eq!(normalize("Öld,", &config), Some("öld".into()));
+        assert_eq!(normalize("of", &config), None);
+        assert_eq!(normalize("--", &config), None);
+    }
+
+    #[test]
+    fn test_tally() {
+        let mut counts = HashMap::new();
+        let text = "the cat\n  and The\tdog\nthe";
+        assert!(tally(Cursor::new(text), &config(), &mut counts).is_ok());
+        assert_eq!(counts.get("the"), Some(&2));
+        assert_eq!(counts.get("The"), Some(&1));
+        assert_eq!(counts.len(), 5);
+
+        let sorted = sort_tally(counts, Some(3));
+        assert_eq!(
+            sorted,
+            vec![
+                ("the".to_string(), 2),
+                ("The".to_string(), 1),
+                ("and".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_counts() {
+        let counts = vec![("a,b".to_string(), 12), ("say \"hi\"".to_string(), 1)];
+        assert_eq!(
+            format_counts(&counts, &FreqFormat::Text),
+            "     12 a,b\n      1 say \"hi\"\n"
+        );
+        assert_eq!(
+            format_counts(&counts, &FreqFormat::Csv),
+            "word,count\n\"a,b\",12\n\"say \"\"hi\"\"\",1\n"
+        );
+        assert_eq!(
+            format_counts(&counts, &FreqFormat::Json),
+            "[{\"count\":12,\"word\":\"a,b\"},\
+             {\"count\":1,\"word\":\"say \\\"hi\\\"\"}]\n"
+        );
+    }
+}
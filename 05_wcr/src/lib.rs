@@ -1,10 +1,22 @@
+mod counter;
+mod freq;
+mod stats;
+
 use clap::{App, Arg};
+use counter::{Counter, Selection};
+use freq::{FreqConfig, FreqFormat};
+use rayon::prelude::*;
+use stats::TextStats;
+use std::collections::HashMap;
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+// Read buffer size, large enough to keep per-read overhead out of the way
+const CHUNK_SIZE: usize = 128 * 1024;
+
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
@@ -12,13 +24,74 @@ pub struct Config {
     words: bool,
     bytes: bool,
     chars: bool,
+    graphemes: bool,
+    unicode_words: bool,
+    max_line_length: bool,
+    total: TotalMode,
+    width: Option<usize>,
+    freq: Option<FreqConfig>,
+    stats: bool,
+    words_per_minute: usize,
+}
+
+impl Config {
+    // which columns to show, in the order they are printed
+    fn columns(&self) -> [bool; 7] {
+        [
+            self.lines,
+            self.words,
+            self.bytes,
+            self.chars,
+            self.graphemes,
+            self.unicode_words,
+            self.max_line_length,
+        ]
+    }
 }
+
 #[derive(Debug, PartialEq)]
+enum TotalMode {
+    Auto,
+    Always,
+    Only,
+    Never,
+}
+
+#[derive(Debug, Default, PartialEq)]
 pub struct FileInfo {
     lines: usize,
     words: usize,
     bytes: usize,
     chars: usize,
+    graphemes: usize,
+    unicode_words: usize,
+    max_line_length: usize,
+    stats: Option<TextStats>,
+}
+
+impl FileInfo {
+    fn columns(&self) -> [usize; 7] {
+        [
+            self.lines,
+            self.words,
+            self.bytes,
+            self.chars,
+            self.graphemes,
+            self.unicode_words,
+            self.max_line_length,
+        ]
+    }
+
+    fn add(&mut self, other: &FileInfo) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.bytes += other.bytes;
+        self.chars += other.chars;
+        self.graphemes += other.graphemes;
+        self.unicode_words += other.unicode_words;
+        // longest line across all files, not a sum
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -35,6 +108,29 @@ pub fn get_args() -> MyResult<Config> {
                 .multiple(true)
                 .default_value("-"),
         )
+        .arg(
+            Arg::with_name("files0_from")
+                .value_name("F")
+                .long("files0-from")
+                .help("Read NUL separated file names from F, - for stdin")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("total")
+                .value_name("WHEN")
+                .long("total")
+                .help("When to print a line with total counts")
+                .possible_values(&["auto", "always", "only", "never"])
+                .default_value("auto")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("width")
+                .value_name("WIDTH")
+                .long("width")
+                .help("Pad each count to WIDTH columns")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("lines")
                 .value_name("LINES")
@@ -68,156 +164,396 @@ pub fn get_args() -> MyResult<Config> {
                 .takes_value(false)
                 .conflicts_with("bytes"),
         )
+        .arg(
+            Arg::with_name("graphemes")
+                .long("graphemes")
+                .help("Prints number of grapheme clusters")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("unicode_words")
+                .long("unicode-words")
+                .help("Prints number of words by Unicode word boundaries")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("max_line_length")
+                .value_name("MAX_LINE_LENGTH")
+                .long("max-line-length")
+                .short("L")
+                .help("Prints display width of the longest line")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .help("Prints sentence, paragraph and reading time statistics")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("words_per_minute")
+                .value_name("N")
+                .long("wpm")
+                .help("Reading speed in words per minute, with --stats")
+                .requires("stats")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("freq")
+                .long("freq")
+                .help("Prints word frequencies across all files instead of counts")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("fold_case")
+                .long("fold-case")
+                .help("Counts words regardless of case, with --freq")
+                .requires("freq")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("strip_punctuation")
+                .long("strip-punctuation")
+                .help("Strips punctuation around words, with --freq")
+                .requires("freq")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("min_length")
+                .value_name("N")
+                .long("min-length")
+                .help("Skips words shorter than N chars, with --freq")
+                .requires("freq")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("top")
+                .value_name("N")
+                .long("top")
+                .help("Prints only the N most frequent words, with --freq")
+                .requires("freq")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .value_name("FORMAT")
+                .long("format")
+                .help("Output format of --freq, text by default")
+                .requires("freq")
+                .possible_values(&["text", "csv", "json"])
+                .takes_value(true),
+        )
         .get_matches();
 
-    let files = matches.values_of_lossy("files").unwrap();
+    let files = match matches.value_of("files0_from") {
+        Some(files0_from) => {
+            if let Some(operand) = matches
+                .values_of_lossy("files")
+                .filter(|_| matches.occurrences_of("files") > 0)
+                .and_then(|files| files.into_iter().next())
+            {
+                return Err(format!(
+                    "extra operand '{}'\n\
+                     file operands cannot be combined with --files0-from",
+                    operand
+                )
+                .into());
+            }
+            read_files0(files0_from)?
+        }
+        None => matches.values_of_lossy("files").unwrap(),
+    };
     let lines = matches.is_present("lines");
     let words = matches.is_present("words");
     let bytes = matches.is_present("bytes");
     let chars = matches.is_present("chars");
+    let graphemes = matches.is_present("graphemes");
+    let unicode_words = matches.is_present("unicode_words");
+    let max_line_length = matches.is_present("max_line_length");
 
-    // if found any flag, use flags
-    if lines || words || bytes || chars {
-        return Ok(Config {
-            files,
-            lines,
-            words,
-            bytes,
-            chars,
-        });
-    }
+    // clap should disallow anything but the listed modes
+    let total = match matches.value_of("total") {
+        Some("always") => TotalMode::Always,
+        Some("only") => TotalMode::Only,
+        Some("never") => TotalMode::Never,
+        _ => TotalMode::Auto,
+    };
+
+    let width = matches
+        .value_of("width")
+        .map(|width| {
+            width
+                .parse::<usize>()
+                .map_err(|_| format!("invalid width -- {}", width))
+        })
+        .transpose()?;
+
+    let freq = if matches.is_present("freq") {
+        Some(FreqConfig {
+            fold_case: matches.is_present("fold_case"),
+            strip_punctuation: matches.is_present("strip_punctuation"),
+            min_length: matches
+                .value_of("min_length")
+                .map(parse_count)
+                .transpose()
+                .map_err(|e| format!("invalid minimum length -- {}", e))?
+                .unwrap_or(0),
+            top: matches
+                .value_of("top")
+                .map(parse_count)
+                .transpose()
+                .map_err(|e| format!("invalid top count -- {}", e))?,
+            // clap should disallow anything but the listed formats
+            format: match matches.value_of("format") {
+                Some("csv") => FreqFormat::Csv,
+                Some("json") => FreqFormat::Json,
+                _ => FreqFormat::Text,
+            },
+        })
+    } else {
+        None
+    };
+
+    let words_per_minute = matches
+        .value_of("words_per_minute")
+        .map(|value| match value.parse() {
+            Ok(words_per_minute) if words_per_minute > 0 => Ok(words_per_minute),
+            _ => Err(format!("invalid reading speed -- {}", value)),
+        })
+        .transpose()?
+        .unwrap_or(stats::READING_SPEED);
 
     // defaults to showing lines, words, and bytes when none of the flags was supplied
+    let defaults =
+        !(lines || words || bytes || chars || graphemes || unicode_words || max_line_length);
+
     Ok(Config {
         files,
-        lines: true,
-        words: true,
-        bytes: true,
-        chars: false,
+        lines: lines || defaults,
+        words: words || defaults,
+        bytes: bytes || defaults,
+        chars,
+        graphemes,
+        unicode_words,
+        max_line_length,
+        total,
+        width,
+        freq,
+        stats: matches.is_present("stats"),
+        words_per_minute,
     })
 }
 
+fn parse_count(value: &str) -> MyResult<usize> {
+    value.parse().map_err(|_| value.into())
+}
+
+fn read_files0(files0_from: &str) -> MyResult<Vec<String>> {
+    let mut buffer = Vec::new();
+    match files0_from {
+        "-" => io::stdin().read_to_end(&mut buffer),
+        _ => File::open(files0_from).and_then(|mut file| file.read_to_end(&mut buffer)),
+    }
+    .map_err(|e| format!("cannot open '{}' for reading: {}", files0_from, e))?;
+
+    let files = parse_files0(&buffer);
+    if files0_from == "-" && files.iter().any(|file| file == "-") {
+        return Err("when reading file names from stdin, \
+                    no file name of '-' allowed"
+            .into());
+    }
+    Ok(files)
+}
+
+fn parse_files0(buffer: &[u8]) -> Vec<String> {
+    // the list may or may not end with a NUL
+    let buffer = buffer.strip_suffix(b"\0").unwrap_or(buffer);
+    if buffer.is_empty() {
+        return vec![];
+    }
+    buffer
+        .split(|byte| *byte == b'\0')
+        .map(|name| String::from_utf8_lossy(name).to_string())
+        .collect()
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    let mut lines_count = 0;
-    let mut words_count = 0;
-    let mut bytes_count = 0;
-    let mut chars_count = 0;
+    if let Some(freq) = &config.freq {
+        return run_freq(&config.files, freq);
+    }
 
-    for filename in &config.files {
-        match open(filename) {
+    let width = config.width.unwrap_or_else(|| number_width(&config));
+    let mut total = FileInfo::default();
+
+    // files are counted in parallel, then reported in the order given
+    let results: Vec<_> = config
+        .files
+        .par_iter()
+        .map(|filename| count_file(filename, &config).map_err(|err| err.to_string()))
+        .collect();
+
+    for (filename, result) in config.files.iter().zip(results) {
+        match result {
+            Err(err) if filename.is_empty() => eprintln!("{}", err),
             Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                if let Ok(FileInfo {
-                    lines,
-                    words,
-                    bytes,
-                    chars,
-                }) = count(file)
-                {
-                    let filename_display = if filename == "-" {
-                        "".to_string()
-                    } else {
-                        (&filename).to_string()
-                    };
+            Ok(info) => {
+                let filename_display = if filename == "-" {
+                    "".to_string()
+                } else {
+                    filename.to_string()
+                };
+                if config.total != TotalMode::Only {
                     println!(
                         "{}",
-                        format_row(
-                            format_value(lines, config.lines),
-                            format_value(words, config.words),
-                            format_value(bytes, config.bytes),
-                            format_value(chars, config.chars),
-                            filename_display,
-                        )
+                        format_row(&format_values(&info, &config, width), filename_display)
                     );
-
-                    lines_count += lines;
-                    words_count += words;
-                    bytes_count += bytes;
-                    chars_count += chars;
+                    if let Some(stats) = &info.stats {
+                        print!("{}", stats::format_stats(stats, config.words_per_minute));
+                    }
                 }
+                total.add(&info);
             }
         }
     }
 
-    if config.files.len() > 1 {
+    let show_total = match config.total {
+        TotalMode::Auto => config.files.len() > 1,
+        TotalMode::Always | TotalMode::Only => true,
+        TotalMode::Never => false,
+    };
+    if show_total {
+        // only the numbers are printed when they are all there is
+        let label = if config.total == TotalMode::Only {
+            "".to_string()
+        } else {
+            "total".to_string()
+        };
         println!(
             "{}",
-            format_row(
-                format_value(lines_count, config.lines),
-                format_value(words_count, config.words),
-                format_value(bytes_count, config.bytes),
-                format_value(chars_count, config.chars),
-                "total".to_string(),
-            )
+            format_row(&format_values(&total, &config, width), label)
         );
     }
 
     Ok(())
 }
 
-fn format_row(
-    lines: String,
-    words: String,
-    bytes: String,
-    chars: String,
-    suffix: String,
-) -> String {
-    let formatted_suffix = if suffix.len() == 0 {
-        suffix
-    } else {
-        format!(" {suffix}")
-    };
-    format!("{lines}{words}{bytes}{chars}{formatted_suffix}")
+fn run_freq(files: &[String], config: &FreqConfig) -> MyResult<()> {
+    let mut counts = HashMap::new();
+    for filename in files {
+        match open(filename) {
+            Err(err) => eprintln!("{}: {}", filename, err),
+            Ok(file) => {
+                if let Err(err) = freq::tally(file, config, &mut counts) {
+                    eprintln!("{}: {}", filename, err);
+                }
+            }
+        }
+    }
+    let counts = freq::sort_tally(counts, config.top);
+    print!("{}", freq::format_counts(&counts, &config.format));
+    Ok(())
+}
+
+// Like GNU wc, counts are separated by a single space and hidden
+// columns, given as empty strings, take no room
+fn format_row(values: &[String], suffix: String) -> String {
+    values
+        .iter()
+        .chain([&suffix])
+        .filter(|column| !column.is_empty())
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_values(info: &FileInfo, config: &Config, width: usize) -> Vec<String> {
+    info.columns()
+        .into_iter()
+        .zip(config.columns())
+        .map(|(value, show)| format_value(value, show, width))
+        .collect()
 }
 
-fn format_value(value: usize, show: bool) -> String {
+fn format_value(value: usize, show: bool, width: usize) -> String {
     if show {
-        format!("{value:>8}")
+        format!("{value:>width$}")
     } else {
         "".to_string()
     }
 }
 
+// Column width as GNU wc picks it before reading anything: wide enough
+// for the total size of the regular files, at least 7 when the size of
+// some input (like stdin) is unknown, and 1 for a single number
+fn number_width(config: &Config) -> usize {
+    let single_number =
+        config.files.len() == 1 && config.columns().iter().filter(|&&show| show).count() == 1;
+    if config.files.is_empty() || single_number {
+        return 1;
+    }
+
+    let mut minimum_width = 1;
+    let mut regular_total = 0;
+    for filename in &config.files {
+        if filename == "-" {
+            minimum_width = 7;
+            continue;
+        }
+        match fs::metadata(filename) {
+            Ok(metadata) if metadata.is_file() => regular_total += metadata.len(),
+            Ok(_) => minimum_width = 7,
+            Err(_) => {}
+        }
+    }
+    regular_total.to_string().len().max(minimum_width)
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+        "-" => Ok(Box::new(BufReader::with_capacity(CHUNK_SIZE, io::stdin()))),
+        _ => Ok(Box::new(BufReader::with_capacity(
+            CHUNK_SIZE,
+            File::open(filename)?,
+        ))),
     }
 }
-// #region count and test
-pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
-    let mut lines = 0;
-    let mut words = 0;
-    let mut bytes = 0;
-    let mut chars = 0;
-    let mut line = String::new();
-
-    loop {
-        let line_bytes = file.read_line(&mut line)?;
-        if line_bytes == 0 {
-            break;
-        }
 
-        lines += 1;
-        words += line.split_whitespace().count();
-        bytes += line_bytes;
-        chars += line.chars().count();
+fn count_file(filename: &str, config: &Config) -> MyResult<FileInfo> {
+    if filename.is_empty() {
+        return Err("invalid zero-length file name".into());
+    }
 
-        // read_line will push new line onto line string, so we have to clear
-        line.clear();
+    // the size of a regular file is all -c needs, no reading required
+    let bytes_only =
+        config.bytes && !config.stats && config.columns().iter().filter(|&&show| show).count() == 1;
+    if bytes_only && filename != "-" {
+        let metadata = fs::metadata(filename)?;
+        if metadata.is_file() {
+            return Ok(FileInfo {
+                bytes: metadata.len() as usize,
+                ..FileInfo::default()
+            });
+        }
     }
 
-    Ok(FileInfo {
-        lines,
-        words,
-        bytes,
-        chars,
-    })
+    let selection = Selection {
+        words: config.words,
+        chars: config.chars,
+        graphemes: config.graphemes,
+        unicode_words: config.unicode_words,
+        max_line_length: config.max_line_length,
+        stats: config.stats,
+    };
+    Counter::new(selection).count(open(filename)?)
+}
+// #region count and test
+pub fn count(file: impl BufRead) -> MyResult<FileInfo> {
+    Counter::new(Selection::all()).count(file)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{count, format_row, format_value, FileInfo};
+    use super::{count, format_row, format_value, parse_files0, FileInfo};
     use std::io::Cursor;
 
     #[test]
@@ -233,28 +569,84 @@ mod tests {
                 words: 10,
                 bytes: 48,
                 chars: 48,
+                // \r\n is a single grapheme cluster
+                graphemes: 47,
+                unicode_words: 10,
+                max_line_length: 46,
+                stats: None,
             }
         )
     }
 
+    #[test]
+    fn test_count_max_line_length() {
+        let text = "short\nthe longest line\nmid line\n";
+        let info = count(Cursor::new(text));
+
+        assert!(info.is_ok());
+        assert_eq!(info.unwrap().max_line_length, 16);
+    }
+
+    #[test]
+    fn test_count_display_width() {
+        let display_width = |text: &str| count(Cursor::new(text)).unwrap().max_line_length;
+
+        assert_eq!(display_width(""), 0);
+        assert_eq!(display_width("abc\n"), 3);
+        // tabs stop at multiples of 8
+        assert_eq!(display_width("\t"), 8);
+        assert_eq!(display_width("abc\tx"), 9);
+        assert_eq!(display_width("12345678\tx"), 17);
+        // East Asian wide chars are 2 columns, combining marks are 0
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("e\u{301}x"), 2);
+        // carriage return starts the line over
+        assert_eq!(display_width("long line\rab\r\n"), 9);
+        assert_eq!(display_width("ab\rlonger line\r\n"), 11);
+    }
+
+    #[test]
+    fn test_parse_files0() {
+        assert_eq!(parse_files0(b""), Vec::<String>::new());
+        assert_eq!(parse_files0(b"a.txt"), vec!["a.txt"]);
+        assert_eq!(parse_files0(b"a.txt\0b c.txt\0"), vec!["a.txt", "b c.txt"]);
+        // empty names are kept so they can be reported
+        assert_eq!(parse_files0(b"a\0\0b"), vec!["a", "", "b"]);
+    }
+
     #[test]
     fn test_format_value() {
-        assert_eq!(format_value(1, false), "");
-        assert_eq!(format_value(3, true), "       3");
-        assert_eq!(format_value(10, true), "      10");
+        assert_eq!(format_value(1, false, 8), "");
+        assert_eq!(format_value(3, true, 8), "       3");
+        assert_eq!(format_value(10, true, 8), "      10");
+        assert_eq!(format_value(10, true, 1), "10");
     }
 
     #[test]
     fn test_format_row() {
         assert_eq!(
             format_row(
-                format_value(1, true),
-                format_value(2, false),
-                format_value(3, true),
-                format_value(4, true),
+                &[
+                    format_value(1, true, 7),
+                    format_value(2, false, 7),
+                    format_value(3, true, 7),
+                    format_value(4, true, 7),
+                    format_value(5, false, 7),
+                ],
                 "file.bar".to_string()
             ),
-            "       1       3       4 file.bar"
+            "      1       3       4 file.bar"
+        );
+        assert_eq!(
+            format_row(
+                &[
+                    format_value(1, false, 1),
+                    format_value(22, true, 1),
+                    format_value(3, false, 1),
+                ],
+                "".to_string()
+            ),
+            "22"
         );
     }
 }
@@ -0,0 +1,146 @@
+// Words a minute used to estimate reading time
+pub const READING_SPEED: usize = 200;
+
+// Content metrics gathered a line at a time. A sentence ends with a
+// word ending in '.', '!' or '?', maybe followed by closing quotes or
+// brackets, or with the paragraph it is in. Paragraphs are separated
+// by blank lines.
+#[derive(Debug, Default, PartialEq)]
+pub struct TextStats {
+    pub sentences: usize,
+    pub paragraphs: usize,
+    pub words: usize,
+    pub longest_word: String,
+    words_in_sentence: usize,
+    in_paragraph: bool,
+}
+
+impl TextStats {
+    pub fn push_line(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            self.end_paragraph();
+            return;
+        }
+        if !self.in_paragraph {
+            self.paragraphs += 1;
+            self.in_paragraph = true;
+        }
+        for word in line.split_whitespace() {
+            self.words += 1;
+            self.words_in_sentence += 1;
+
+            let bare = word.trim_matches(|c: char| !c.is_alphanumeric());
+            if bare.chars().count() > self.longest_word.chars().count() {
+                self.longest_word = bare.to_string();
+            }
+
+            let ending = word.trim_end_matches(['"', '\'', ')', ']', '”', '’']);
+            if ending.ends_with(['.', '!', '?']) {
+                self.end_sentence();
+            }
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.end_paragraph();
+    }
+
+    pub fn words_per_sentence(&self) -> f64 {
+        if self.sentences == 0 {
+            0.0
+        } else {
+            self.words as f64 / self.sentences as f64
+        }
+    }
+
+    // Whole minutes, rounded up so any text takes at least a minute
+    pub fn reading_minutes(&self, words_per_minute: usize) -> usize {
+        self.words.div_ceil(words_per_minute)
+    }
+
+    fn end_sentence(&mut self) {
+        if self.words_in_sentence > 0 {
+            self.sentences += 1;
+            self.words_in_sentence = 0;
+        }
+    }
+
+    fn end_paragraph(&mut self) {
+        self.end_sentence();
+        self.in_paragraph = false;
+    }
+}
+
+pub fn format_stats(stats: &TextStats, words_per_minute: usize) -> String {
+    [
+        ("sentences", stats.sentences.to_string()),
+        ("paragraphs", stats.paragraphs.to_string()),
+        (
+            "words per sentence",
+            format!("{:.1}", stats.words_per_sentence()),
+        ),
+        ("longest word", stats.longest_word.clone()),
+        (
+            "reading time",
+            format!("{} min", stats.reading_minutes(words_per_minute)),
+        ),
+    ]
+    .iter()
+    .map(|(label, value)| {
+        let line = format!("    {:<20}{}", format!("{label}:"), value);
+        format!("{}\n", line.trim_end())
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_stats, TextStats};
+
+    fn stats_of(text: &str) -> TextStats {
+        let mut stats = TextStats::default();
+        for line in text.lines() {
+            stats.push_line(line);
+        }
+        stats.finish();
+        stats
+    }
+
+    #[test]
+    fn test_sentences_and_paragraphs() {
+        let stats = stats_of(
+            "A Title\n\
+             \n\
+             One sentence. \"Another one!\" Is it\n\
+             a third? Yes.\n\
+             \n  \n\
+             Last paragraph, no period\n",
+        );
+        assert_eq!(stats.paragraphs, 3);
+        // the title and the last paragraph end sentences of their own
+        assert_eq!(stats.sentences, 6);
+        assert_eq!(stats.words, 15);
+        assert_eq!(stats.longest_word, "paragraph");
+    }
+
+    #[test]
+    fn test_empty() {
+        let stats = stats_of("");
+        assert_eq!(stats, TextStats::default());
+        assert_eq!(stats.words_per_sentence(), 0.0);
+        assert_eq!(stats.reading_minutes(200), 0);
+    }
+
+    #[test]
+    fn test_format_stats() {
+        let stats = stats_of("Frétt hefir öld óvu. Þá er endr of gerðu.\n");
+        assert_eq!(
+            format_stats(&stats, 200),
+            "    sentences:          2\n\
+             \x20   paragraphs:         1\n\
+             \x20   words per sentence: 4.5\n\
+             \x20   longest word:       Frétt\n\
+             \x20   reading time:       1 min\n"
+        );
+    }
+}
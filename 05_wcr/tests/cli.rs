@@ -9,6 +9,8 @@ const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const WIDE: &str = "tests/inputs/wide.txt";
+const FILES0: &str = "tests/inputs/files0.txt";
 
 // --------------------------------------------------
 fn gen_bad_file() -> String {
@@ -29,7 +31,7 @@ fn gen_bad_file() -> String {
 #[test]
 fn dies_chars_and_bytes() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "-c"])
+        .args(["-m", "-c"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(
@@ -38,6 +40,74 @@ fn dies_chars_and_bytes() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_files0_from_and_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", FILES0, FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!("extra operand '{}'", FOX)));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_total() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--total", "sometimes", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "'sometimes' isn't a valid value for '--total <WHEN>'",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_width() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--width", "wide", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid width -- wide"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_freq_option_without_freq() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--top", "3", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--freq"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_top() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--freq", "--top", "many", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid top count -- many"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_wpm() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--stats", "--wpm", "0", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid reading speed -- 0"));
+    Ok(())
+}
+
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
     let expected = fs::read_to_string(expected_file)?;
@@ -62,6 +132,20 @@ fn skips_bad_file() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn skips_unreadable_file() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", FOX])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match(
+            "tests/inputs: .* [(]os error 21[)]",
+        )?)
+        .stdout(predicate::str::contains(FOX));
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn empty() -> TestResult {
@@ -116,6 +200,21 @@ fn fox_bytes_lines() -> TestResult {
     run(&["-l", "-c", FOX], "tests/expected/fox.txt.cl.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn fox_width() -> TestResult {
+    run(
+        &["--width", "10", FOX],
+        "tests/expected/fox.txt.width10.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_max_line_length() -> TestResult {
+    run(&["--max-line-length", FOX], "tests/expected/fox.txt.L.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn atlamal() -> TestResult {
@@ -158,6 +257,36 @@ fn atlamal_bytes_lines() -> TestResult {
     run(&["-l", "-c", ATLAMAL], "tests/expected/atlamal.txt.cl.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn atlamal_max_line_length() -> TestResult {
+    run(&["-L", ATLAMAL], "tests/expected/atlamal.txt.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn wide_max_line_length() -> TestResult {
+    run(&["-L", WIDE], "tests/expected/wide.txt.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn wide_graphemes_unicode_words() -> TestResult {
+    run(
+        &["-wm", "--graphemes", "--unicode-words", WIDE],
+        "tests/expected/wide.txt.wm.graphemes.unicode-words.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn atlamal_words_stats_wpm() -> TestResult {
+    run(
+        &["-w", "--stats", "--wpm", "10", ATLAMAL],
+        "tests/expected/atlamal.txt.w.stats-wpm10.out",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn atlamal_stdin() -> TestResult {
@@ -212,3 +341,127 @@ fn test_all_words_lines() -> TestResult {
 fn test_all_bytes_lines() -> TestResult {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn test_all_lines_max_line_length() -> TestResult {
+    run(&["-lL", EMPTY, FOX, ATLAMAL], "tests/expected/all.lL.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_graphemes_unicode_words() -> TestResult {
+    run(
+        &["--graphemes", "--unicode-words", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.graphemes.unicode-words.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_stats() -> TestResult {
+    run(
+        &["--stats", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.stats.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from() -> TestResult {
+    run(&["--files0-from", FILES0], "tests/expected/files0.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_stdin() -> TestResult {
+    let input = fs::read(FILES0)?;
+    let expected = fs::read_to_string("tests/expected/files0.txt.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", "-"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_zero_length_name() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", "-", "--total", "never"])
+        .write_stdin(format!("{}\0\0", FOX))
+        .assert()
+        .success()
+        .stderr("invalid zero-length file name\n")
+        .stdout(fs::read_to_string("tests/expected/fox.txt.out")?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_total_always() -> TestResult {
+    run(
+        &["--total", "always", FOX],
+        "tests/expected/fox.txt.total-always.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_total_only() -> TestResult {
+    run(
+        &["--total", "only", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total-only.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_total_never() -> TestResult {
+    run(
+        &["--total", "never", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total-never.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_freq() -> TestResult {
+    run(
+        &["--freq", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.freq.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_freq_csv() -> TestResult {
+    run(
+        &[
+            "--freq",
+            "--fold-case",
+            "--strip-punctuation",
+            "--min-length",
+            "3",
+            "--top",
+            "5",
+            "--format",
+            "csv",
+            EMPTY,
+            FOX,
+            ATLAMAL,
+        ],
+        "tests/expected/all.freq.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_freq_json() -> TestResult {
+    run(
+        &["--freq", "--top", "3", "--format", "json", FOX],
+        "tests/expected/fox.txt.freq.json.out",
+    )
+}
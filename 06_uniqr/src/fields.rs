@@ -0,0 +1,149 @@
+use crate::MyResult;
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use regex::Regex;
+use std::{num::NonZeroUsize, ops::Range};
+
+pub type PositionList = Vec<Range<usize>>;
+
+// Ensures the number does not start with '+'.
+// Returns an index, which is a non-negative integer that is
+// one less than the number represented by the original input.
+fn parse_index(input: &str) -> Result<usize, String> {
+    let value_error = || format!("illegal list value: \"{}\"", input);
+    if input.starts_with('+') {
+        return Err(value_error());
+    }
+    input
+        .parse::<NonZeroUsize>()
+        .map(|n| usize::from(n) - 1)
+        .map_err(|_| value_error())
+}
+
+// Each position is a number N or a range N-M, N- to the end of the
+// line or -M from the start. The crates share no code, so this and
+// parse_index are copies of book_repo/08_cutr/src/lib.rs::parse_pos,
+// which is the source of truth; change both together.
+pub fn parse_pos(range: &str) -> MyResult<PositionList> {
+    let range_re = Regex::new(r"^(\d+)?-(\d+)?$").unwrap();
+    range
+        .split(',')
+        .map(|val| {
+            parse_index(val).map(|n| n..n + 1).or_else(|e| {
+                let captures = match range_re.captures(val) {
+                    Some(captures) => captures,
+                    None => return Err(e),
+                };
+                let index = |i| {
+                    captures
+                        .get(i)
+                        .map(|m: regex::Match| parse_index(m.as_str()))
+                        .transpose()
+                };
+                match (index(1)?, index(2)?) {
+                    (None, None) => Err(e),
+                    (Some(n1), Some(n2)) if n1 > n2 => Err(format!(
+                        "First number in range ({}) \
+                        must not be greater than second number ({})",
+                        n1 + 1,
+                        n2 + 1
+                    )),
+                    (n1, n2) => Ok(n1.unwrap_or(0)..n2.map_or(usize::MAX, |n| n + 1)),
+                }
+            })
+        })
+        .collect::<Result<_, _>>()
+        .map_err(From::from)
+}
+
+// The selected fields of a line as cutr -f prints them. The line is
+// read as a CSV record, so a quoted field may hold the delimiter, and
+// the fields are written as one, quoted where needed, so that no two
+// lists of fields give the same key.
+pub fn extract_key(line: &str, delimiter: u8, field_pos: &[Range<usize>]) -> MyResult<String> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .from_reader(line.as_bytes());
+    let mut record = StringRecord::new();
+    reader.read_record(&mut record)?;
+    let fields: Vec<_> = field_pos
+        .iter()
+        .cloned()
+        .flat_map(|range| (range.start..range.end.min(record.len())).filter_map(|i| record.get(i)))
+        .collect();
+    // nothing or a single empty field is written as "", but "" is
+    // enough to tell either from any other key
+    if fields.iter().all(|field| field.is_empty()) && fields.len() < 2 {
+        return Ok(String::new());
+    }
+    let mut writer = WriterBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_writer(vec![]);
+    writer.write_record(&fields)?;
+    let mut key = String::from_utf8(writer.into_inner()?)?;
+    key.truncate(key.trim_end_matches('\n').len());
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_key, parse_pos};
+
+    #[test]
+    fn test_parse_pos() {
+        assert!(parse_pos("").is_err());
+        assert!(parse_pos("1,").is_err());
+        assert!(parse_pos("-").is_err());
+
+        let res = parse_pos("0");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"0\"");
+
+        let res = parse_pos("+1");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"+1\"");
+
+        let res = parse_pos("1-a");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"1-a\"");
+
+        let res = parse_pos("2-1");
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "First number in range (2) must not be greater than second number (1)"
+        );
+
+        let res = parse_pos("1-1");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..1]);
+
+        let res = parse_pos("2-,-1");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![1..usize::MAX, 0..1]);
+
+        let res = parse_pos("1,7,3-5");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..1, 6..7, 2..5]);
+    }
+
+    #[test]
+    fn test_extract_key() {
+        let key = |line, delimiter, list| {
+            extract_key(line, delimiter, &parse_pos(list).unwrap()).unwrap()
+        };
+        let line = "2,\"Lee, Jo\",Oslo\n";
+        assert_eq!(key(line, b',', "1"), "2");
+        assert_eq!(key(line, b',', "2"), "\"Lee, Jo\"");
+        assert_eq!(key(line, b',', "3,1"), "Oslo,2");
+        assert_eq!(key(line, b',', "4"), "");
+        assert_eq!(key(line, b',', "2-"), "\"Lee, Jo\",Oslo");
+        assert_eq!(key(line, b',', "-2"), "2,\"Lee, Jo\"");
+        // fields holding the delimiter can't run together
+        assert_ne!(key("\"a,b\",c", b',', "1-2"), key("a,\"b,c\"", b',', "1-2"));
+        assert_eq!(key("a,,", b',', "2-"), ",");
+        assert_eq!(key("a\tb", b'\t', "1-2"), "a\tb");
+        assert_eq!(key("", b',', "1"), "");
+    }
+}
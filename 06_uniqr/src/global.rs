@@ -0,0 +1,169 @@
+use crate::MyResult;
+use std::collections::HashSet;
+use std::mem::size_of;
+
+// Size of the Bloom filter when no --max-memory is given
+pub const DEFAULT_BLOOM_BYTES: usize = 16 * 1024 * 1024;
+
+// Bits set per line. Seven is the best choice while the filter holds
+// about one line per ten bits, which keeps false positives near 1%.
+const BLOOM_HASHES: u64 = 7;
+
+// How --global remembers the lines it has already printed
+#[derive(Debug, PartialEq)]
+pub enum Method {
+    // a 128-bit digest of each line, so a line is only dropped as a
+    // duplicate if its digest collides, which in practice never happens
+    Exact,
+    // a fixed size Bloom filter, which never runs out of memory but
+    // drops a line now and then that it wrongly takes for a duplicate
+    Bloom,
+}
+
+pub enum SeenLines {
+    Exact {
+        digests: HashSet<u128>,
+        max_memory: Option<usize>,
+    },
+    Bloom {
+        bits: Vec<u64>,
+    },
+}
+
+impl SeenLines {
+    pub fn new(method: &Method, max_memory: Option<usize>) -> SeenLines {
+        match method {
+            Method::Exact => SeenLines::Exact {
+                digests: HashSet::new(),
+                max_memory,
+            },
+            Method::Bloom => {
+                let bytes = max_memory.unwrap_or(DEFAULT_BLOOM_BYTES);
+                let words = (bytes / size_of::<u64>()).max(1);
+                SeenLines::Bloom {
+                    bits: vec![0; words],
+                }
+            }
+        }
+    }
+
+    // Remembers a line, returning whether it is new
+    pub fn insert(&mut self, key: &str) -> MyResult<bool> {
+        let hash = blake3::hash(key.as_bytes());
+        let digest = u128::from_le_bytes(hash.as_bytes()[..16].try_into()?);
+        match self {
+            SeenLines::Exact {
+                digests,
+                max_memory,
+            } => {
+                if let Some(max_memory) = max_memory {
+                    // the set doubles when full, with a control byte
+                    // for every slot
+                    if digests.len() == digests.capacity() {
+                        let slots = (digests.capacity() * 2).max(4);
+                        if slots * (size_of::<u128>() + 1) > *max_memory {
+                            return Err(format!(
+                                "memory limit exceeded after {} distinct lines, \
+                                 try --global=bloom",
+                                digests.len()
+                            )
+                            .into());
+                        }
+                    }
+                }
+                Ok(digests.insert(digest))
+            }
+            SeenLines::Bloom { bits } => {
+                // the two halves of the digest are combined into the
+                // indexes of the bits, as in Kirsch and Mitzenmacher
+                let len = bits.len() as u64 * 64;
+                let (first, second) = (digest as u64, (digest >> 64) as u64);
+                let mut new = false;
+                for i in 0..BLOOM_HASHES {
+                    let bit = first.wrapping_add(i.wrapping_mul(second)) % len;
+                    let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+                    if bits[word] & mask == 0 {
+                        bits[word] |= mask;
+                        new = true;
+                    }
+                }
+                Ok(new)
+            }
+        }
+    }
+}
+
+// Parses sizes such as 4096, 512K, 64M or 2G, in powers of 1024
+pub fn parse_size(value: &str) -> MyResult<usize> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, ""),
+    };
+    let scale: usize = match unit {
+        "" => 1,
+        "K" | "k" => 1 << 10,
+        "M" | "m" => 1 << 20,
+        "G" | "g" => 1 << 30,
+        _ => return Err(value.into()),
+    };
+    match number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(scale))
+    {
+        Some(size) if size > 0 => Ok(size),
+        _ => Err(value.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_size, Method, SeenLines};
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("512K").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("64m").unwrap(), 64 * 1024 * 1024);
+        assert_eq!(parse_size("2G").unwrap(), 2 * 1024 * 1024 * 1024);
+        for bad in ["", "0", "M", "12T", "1.5M", "-1"] {
+            let res = parse_size(bad);
+            assert!(res.is_err(), "{}", bad);
+            assert_eq!(res.unwrap_err().to_string(), bad);
+        }
+    }
+
+    #[test]
+    fn test_exact() {
+        let mut seen = SeenLines::new(&Method::Exact, None);
+        assert!(seen.insert("a").unwrap());
+        assert!(seen.insert("b").unwrap());
+        assert!(!seen.insert("a").unwrap());
+        assert!(seen.insert("").unwrap());
+        assert!(!seen.insert("").unwrap());
+    }
+
+    #[test]
+    fn test_exact_max_memory() {
+        let mut seen = SeenLines::new(&Method::Exact, Some(1024));
+        let res: Result<Vec<_>, _> = (0..1000).map(|n| seen.insert(&n.to_string())).collect();
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .starts_with("memory limit exceeded after"));
+    }
+
+    #[test]
+    fn test_bloom() {
+        let mut seen = SeenLines::new(&Method::Bloom, Some(64 * 1024));
+        let lines: Vec<_> = (0..5000).map(|n| format!("line {}", n)).collect();
+        let new = lines
+            .iter()
+            .filter(|line| seen.insert(line).unwrap())
+            .count();
+        // a 512K bit filter holding 5000 lines should make no mistakes
+        assert_eq!(new, lines.len());
+        assert!(lines.iter().all(|line| !seen.insert(line).unwrap()));
+    }
+}
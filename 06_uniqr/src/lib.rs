@@ -1,17 +1,49 @@
 use clap::{App, Arg};
+use fields::PositionList;
+use global::{Method, SeenLines};
+use output::Output;
 use std::{
+    borrow::Cow,
     error::Error,
     fs::File,
     io::{self, BufRead, BufReader, Write},
 };
 
+mod fields;
+mod global;
+mod output;
+
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
     in_file: String,
     out_file: Option<String>,
+    tee: Vec<String>,
     count: bool,
+    repeated: bool,
+    all_repeated: Option<Delimit>,
+    unique: bool,
+    ignore_case: bool,
+    ignore_trailing_space: bool,
+    skip_fields: usize,
+    skip_chars: usize,
+    check_chars: Option<usize>,
+    global: Option<Method>,
+    max_memory: Option<usize>,
+    group: Option<Delimit>,
+    key: Option<PositionList>,
+    delimiter: u8,
+}
+
+// How --all-repeated and --group separate groups of lines
+#[derive(Debug, PartialEq)]
+enum Delimit {
+    None,
+    Prepend,
+    Separate,
+    Append,
+    Both,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -31,6 +63,14 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("INPUT_FILE")
                 .help("The output file is the second positional argument and is optional"),
         )
+        .arg(
+            Arg::with_name("tee")
+                .value_name("FILE")
+                .long("tee")
+                .help("Also write the output to FILE, may be repeated")
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::with_name("count")
                 .value_name("COUNT")
@@ -39,61 +79,392 @@ pub fn get_args() -> MyResult<Config> {
                 .help("The -c | --count flag is optional")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("repeated")
+                .short("d")
+                .long("repeated")
+                .help("Only print duplicate lines, one for each group")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("all_repeated")
+                .value_name("METHOD")
+                .short("D")
+                .long("all-repeated")
+                .help("Print all duplicate lines, groups delimited by METHOD")
+                .possible_values(&["none", "prepend", "separate"])
+                .min_values(0)
+                .require_equals(true),
+        )
+        .arg(
+            Arg::with_name("unique")
+                .short("u")
+                .long("unique")
+                .help("Only print unique lines")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("ignore_case")
+                .short("i")
+                .long("ignore-case")
+                .help("Ignore differences in case when comparing lines")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("ignore_trailing_space")
+                .long("ignore-trailing-space")
+                .help("Ignore whitespace, including the CR of a CRLF, at the end of lines when comparing them")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("skip_fields")
+                .value_name("N")
+                .short("f")
+                .long("skip-fields")
+                .help("Avoid comparing the first N fields")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("skip_chars")
+                .value_name("N")
+                .short("s")
+                .long("skip-chars")
+                .help("Avoid comparing the first N characters")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("check_chars")
+                .value_name("N")
+                .short("w")
+                .long("check-chars")
+                .help("Compare no more than N characters in lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("global")
+                .value_name("METHOD")
+                .long("global")
+                .help("Remove duplicates anywhere in the input, keeping the first")
+                .possible_values(&["exact", "bloom"])
+                .min_values(0)
+                .require_equals(true)
+                .conflicts_with_all(&["count", "repeated", "all_repeated", "unique"]),
+        )
+        .arg(
+            Arg::with_name("max_memory")
+                .value_name("SIZE")
+                .long("max-memory")
+                .help("Limit the memory --global uses to remember lines")
+                .requires("global")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("group")
+                .value_name("METHOD")
+                .long("group")
+                .help("Print all lines, groups delimited by METHOD")
+                .possible_values(&["separate", "prepend", "append", "both"])
+                .min_values(0)
+                .require_equals(true)
+                .conflicts_with_all(&["count", "repeated", "all_repeated", "unique", "global"]),
+        )
+        .arg(
+            Arg::with_name("key")
+                .value_name("FIELDS")
+                .long("key")
+                .help("Compare only the selected fields, as in cutr -f")
+                .takes_value(true)
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::with_name("delimiter")
+                .value_name("DELIMITER")
+                .long("delimiter")
+                .help("Field delimiter for --key, a tab by default")
+                .requires("key"),
+        )
         .get_matches();
 
+    // clap should disallow anything but the listed methods
+    let all_repeated = if matches.is_present("all_repeated") {
+        match matches.value_of("all_repeated") {
+            Some("prepend") => Some(Delimit::Prepend),
+            Some("separate") => Some(Delimit::Separate),
+            _ => Some(Delimit::None),
+        }
+    } else {
+        None
+    };
+
+    let skip_fields = matches
+        .value_of("skip_fields")
+        .map(parse_count)
+        .transpose()
+        .map_err(|e| format!("illegal field count -- {}", e))?;
+    let skip_chars = matches
+        .value_of("skip_chars")
+        .map(parse_count)
+        .transpose()
+        .map_err(|e| format!("illegal char count -- {}", e))?;
+    let check_chars = matches
+        .value_of("check_chars")
+        .map(parse_count)
+        .transpose()
+        .map_err(|e| format!("illegal char count -- {}", e))?;
+
+    let global = if matches.is_present("global") {
+        match matches.value_of("global") {
+            Some("bloom") => Some(Method::Bloom),
+            _ => Some(Method::Exact),
+        }
+    } else {
+        None
+    };
+    let group = if matches.is_present("group") {
+        match matches.value_of("group") {
+            Some("prepend") => Some(Delimit::Prepend),
+            Some("append") => Some(Delimit::Append),
+            Some("both") => Some(Delimit::Both),
+            _ => Some(Delimit::Separate),
+        }
+    } else {
+        None
+    };
+    let key = matches.value_of("key").map(fields::parse_pos).transpose()?;
+    let delimiter = matches.value_of("delimiter").unwrap_or("\t");
+    let delim_bytes = delimiter.as_bytes();
+    if delim_bytes.len() != 1 {
+        return Err(From::from(format!(
+            "--delimiter \"{}\" must be a single byte",
+            delimiter
+        )));
+    }
+
+    let max_memory = matches
+        .value_of("max_memory")
+        .map(global::parse_size)
+        .transpose()
+        .map_err(|e| format!("illegal memory size -- {}", e))?;
+
     Ok(Config {
         in_file: matches.value_of("input_file").unwrap().to_string(),
         out_file: matches.value_of("output_file").map(String::from),
+        tee: matches.values_of_lossy("tee").unwrap_or_default(),
         count: matches.is_present("count"),
+        repeated: matches.is_present("repeated"),
+        all_repeated,
+        unique: matches.is_present("unique"),
+        ignore_case: matches.is_present("ignore_case"),
+        ignore_trailing_space: matches.is_present("ignore_trailing_space"),
+        skip_fields: skip_fields.unwrap_or(0),
+        skip_chars: skip_chars.unwrap_or(0),
+        check_chars,
+        global,
+        max_memory,
+        group,
+        key,
+        delimiter: delim_bytes[0],
     })
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let mut in_file = open(&config.in_file).map_err(|e| format!("{}: {}", config.in_file, e))?;
+    let in_file = open(&config.in_file).map_err(|e| format!("{}: {}", config.in_file, e))?;
 
-    let mut out_file: Box<dyn Write> = match &config.out_file {
-        Some(out_name) => Box::new(File::create(out_name)?),
-        _ => Box::new(io::stdout()),
-    };
+    // output files are only replaced once all the input is read, but
+    // refuse to replace the input all the same
+    let mut output = Output::new();
+    for out_name in config.out_file.iter().chain(&config.tee) {
+        if output::same_file(&config.in_file, out_name) {
+            return Err(format!("{}: input and output are the same file", out_name).into());
+        }
+        output.add_file(out_name)?;
+    }
+    if config.out_file.is_none() {
+        output.add_stdout();
+    }
 
-    let mut print = |count: u32, text: String| -> MyResult<()> {
-        if count > 0 {
-            if config.count {
-                write!(out_file, "{count:4} {text}")?;
-            } else {
-                write!(out_file, "{text}")?;
-            };
-        };
-        Ok(())
-    };
+    match &config.global {
+        Some(method) => {
+            let seen = SeenLines::new(method, config.max_memory);
+            print_first_seen(in_file, &mut output, seen, &config)?;
+        }
+        None => print_adjacent(in_file, &mut output, &config)?,
+    }
+    output.finish()
+}
 
+// Prints the groups of adjacent equal lines the flags select
+fn print_adjacent(
+    mut in_file: impl BufRead,
+    out_file: &mut impl Write,
+    config: &Config,
+) -> MyResult<()> {
     let mut line = String::new();
-    let mut previous_line = String::new();
+    // the lines of the current group, only the first unless all are printed
+    let mut group: Vec<String> = vec![];
+    let mut group_key = String::new();
     let mut count: u32 = 0;
+    let mut groups_printed = 0;
 
     loop {
-        let bytes = in_file.read_line(&mut line)?;
+        let bytes = read_line(&mut in_file, &mut line)?;
         // terminate loop on EOF
         if bytes == 0 {
             break;
         }
 
-        // check line content excluding line endings
-        if line.trim_end() != previous_line.trim_end() {
-            print(count, previous_line)?;
-            previous_line = line.clone();
+        let key = compare_key(&line, config)?;
+        if group.is_empty() || key != group_key {
+            if print_group(out_file, &group, count, groups_printed, config)? {
+                groups_printed += 1;
+            }
+            group.clear();
+            group_key = key.into_owned();
             count = 0;
         }
+        if group.is_empty() || config.all_repeated.is_some() || config.group.is_some() {
+            group.push(line.clone());
+        }
         count += 1;
         line.clear(); // otherwise read_line will append more bytes to line
     }
 
     // print last results
-    print(count, previous_line)?;
+    if print_group(out_file, &group, count, groups_printed, config)? {
+        groups_printed += 1;
+    }
+    if let Some(Delimit::Append | Delimit::Both) = config.group {
+        if groups_printed > 0 {
+            writeln!(out_file)?;
+        }
+    }
+
+    Ok(())
+}
 
+// Prints each line the first time it is seen, wherever it appears
+fn print_first_seen(
+    mut in_file: impl BufRead,
+    out_file: &mut impl Write,
+    mut seen: SeenLines,
+    config: &Config,
+) -> MyResult<()> {
+    let mut line = String::new();
+    while read_line(&mut in_file, &mut line)? > 0 {
+        if seen.insert(&compare_key(&line, config)?)? {
+            write!(out_file, "{line}")?;
+        }
+        line.clear();
+    }
     Ok(())
 }
 
+// Reads a line as BufRead::read_line does, adding the newline a last
+// line may lack, so it is printed with one as by GNU uniq
+fn read_line(in_file: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    let bytes = in_file.read_line(line)?;
+    if bytes > 0 && !line.ends_with('\n') {
+        line.push('\n');
+    }
+    Ok(bytes)
+}
+
+fn parse_count(value: &str) -> MyResult<usize> {
+    value.parse().map_err(|_| value.into())
+}
+
+// The part of a line that is compared, excluding the line ending, so a
+// last line without one matches the same line with one: the --key
+// fields if given, then the first fields and chars are skipped, as with
+// GNU uniq a field being blanks followed by non-blanks, and at most
+// check_chars of what is left is used
+fn compare_key<'a>(line: &'a str, config: &Config) -> MyResult<Cow<'a, str>> {
+    let line = if config.ignore_trailing_space {
+        line.trim_end()
+    } else {
+        strip_line_ending(line)
+    };
+    Ok(match &config.key {
+        Some(field_pos) => {
+            // the csv reader would end the record at a CR, so it is
+            // kept out of the record and put back at the end of the key
+            let (line, cr) = match line.strip_suffix('\r') {
+                Some(line) => (line, "\r"),
+                None => (line, ""),
+            };
+            let key = fields::extract_key(line, config.delimiter, field_pos)? + cr;
+            Cow::Owned(fold_case(select_chars(&key, config), config).into_owned())
+        }
+        None => fold_case(select_chars(line, config), config),
+    })
+}
+
+// Removes a trailing newline, keeping any other whitespace. As with
+// GNU uniq, the CR of a CRLF is kept, so a\r\n and a\n are different
+// lines unless --ignore-trailing-space is given.
+fn strip_line_ending(line: &str) -> &str {
+    line.strip_suffix('\n').unwrap_or(line)
+}
+
+fn select_chars<'a>(line: &'a str, config: &Config) -> &'a str {
+    let mut key = line;
+    for _ in 0..config.skip_fields {
+        key = key.trim_start_matches([' ', '\t']);
+        key = key.trim_start_matches(|c| c != ' ' && c != '\t');
+    }
+    key = skip_chars(key, config.skip_chars);
+    if let Some(check_chars) = config.check_chars {
+        key = &key[..key.len() - skip_chars(key, check_chars).len()];
+    }
+    key
+}
+
+fn fold_case<'a>(key: &'a str, config: &Config) -> Cow<'a, str> {
+    if config.ignore_case {
+        Cow::Owned(key.to_lowercase())
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+fn skip_chars(text: &str, count: usize) -> &str {
+    match text.char_indices().nth(count) {
+        Some((index, _)) => &text[index..],
+        None => "",
+    }
+}
+
+// Prints a group of equal lines if the flags select it, returning
+// whether anything was printed
+fn print_group(
+    out_file: &mut impl Write,
+    group: &[String],
+    count: u32,
+    groups_printed: usize,
+    config: &Config,
+) -> MyResult<bool> {
+    let repeated = config.repeated || config.all_repeated.is_some();
+    if count == 0 || (repeated && count == 1) || (config.unique && count > 1) {
+        return Ok(false);
+    }
+
+    match config.all_repeated.as_ref().or(config.group.as_ref()) {
+        Some(Delimit::Prepend | Delimit::Both) => writeln!(out_file)?,
+        Some(Delimit::Separate | Delimit::Append) if groups_printed > 0 => writeln!(out_file)?,
+        _ => {}
+    }
+    // all lines were kept for --all-repeated and --group, otherwise only
+    // the first
+    for text in group {
+        if config.count {
+            write!(out_file, "{count:4} {text}")?;
+        } else {
+            write!(out_file, "{text}")?;
+        }
+    }
+    Ok(true)
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
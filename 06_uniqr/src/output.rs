@@ -0,0 +1,213 @@
+use crate::MyResult;
+#[cfg(unix)]
+use std::os::unix::fs::{fchown, MetadataExt, PermissionsExt};
+use std::{
+    fs::{self, File, Metadata, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use tempfile::{Builder, TempPath};
+
+// Writes the same output to stdout and any number of files. A regular
+// file is written to a temporary file next to it, which only replaces
+// it once finish is called, so a failed run never leaves a file half
+// written. Anything else, like a FIFO or a device, is written in place.
+pub struct Output {
+    writers: Vec<Box<dyn Write>>,
+    temp_paths: Vec<(TempPath, PathBuf)>,
+}
+
+impl Output {
+    pub fn new() -> Output {
+        Output {
+            writers: vec![],
+            temp_paths: vec![],
+        }
+    }
+
+    pub fn add_stdout(&mut self) {
+        self.writers.push(Box::new(io::stdout()));
+    }
+
+    pub fn add_file(&mut self, filename: &str) -> MyResult<()> {
+        let error = |e: io::Error| format!("{}: {}", filename, e);
+        // a symbolic link is followed to the file it points to
+        let metadata = match fs::metadata(filename) {
+            Ok(metadata) => Some(metadata),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(error(e).into()),
+        };
+        match metadata {
+            None if fs::symlink_metadata(filename).is_err() => {
+                let (file, temp_path) = temp_file(Path::new(filename)).map_err(error)?;
+                self.add_temp_file(file, temp_path, PathBuf::from(filename));
+            }
+            Some(metadata) if metadata.is_file() => {
+                // fails as writing in place would, as for a read-only file
+                OpenOptions::new()
+                    .write(true)
+                    .open(filename)
+                    .map_err(error)?;
+                let path = fs::canonicalize(filename).map_err(error)?;
+                let (file, temp_path) = temp_file(&path).map_err(error)?;
+                keep_mode_and_owner(&file, &metadata).map_err(error)?;
+                self.add_temp_file(file, temp_path, path);
+            }
+            // a FIFO or device, or a link to a file that doesn't exist yet
+            _ => {
+                let file = File::create(filename).map_err(error)?;
+                self.writers.push(Box::new(BufWriter::new(file)));
+            }
+        }
+        Ok(())
+    }
+
+    fn add_temp_file(&mut self, file: File, temp_path: TempPath, path: PathBuf) {
+        self.writers.push(Box::new(BufWriter::new(file)));
+        self.temp_paths.push((temp_path, path));
+    }
+
+    pub fn finish(mut self) -> MyResult<()> {
+        self.flush()?;
+        // close the files before they are moved into place
+        self.writers.clear();
+        for (temp_path, path) in self.temp_paths {
+            temp_path
+                .persist(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+// Creates a temporary file in the directory of a path, with the mode
+// File::create would give a new file
+fn temp_file(path: &Path) -> io::Result<(File, TempPath)> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut builder = Builder::new();
+    builder.prefix(".uniqr");
+    #[cfg(unix)]
+    builder.permissions(fs::Permissions::from_mode(0o666));
+    let (file, temp_path) = builder.tempfile_in(dir)?.into_parts();
+    Ok((file, temp_path))
+}
+
+// Gives a temporary file the mode of the file it replaces whatever the
+// umask, and its owner where allowed, as for root
+#[cfg(unix)]
+fn keep_mode_and_owner(file: &File, metadata: &Metadata) -> io::Result<()> {
+    file.set_permissions(metadata.permissions())?;
+    let _ = fchown(file, Some(metadata.uid()), Some(metadata.gid()));
+    Ok(())
+}
+
+// Elsewhere the only permission is the read-only flag, which a file that
+// can be replaced doesn't have
+#[cfg(not(unix))]
+fn keep_mode_and_owner(_file: &File, _metadata: &Metadata) -> io::Result<()> {
+    Ok(())
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for writer in &mut self.writers {
+            writer.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for writer in &mut self.writers {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// Whether two names refer to the same file, with "-" for stdin. A file
+// that doesn't exist is never the same as another.
+#[cfg(unix)]
+pub fn same_file(filename: &str, other: &str) -> bool {
+    let metadata = |name: &str| match name {
+        "-" => fs::metadata("/dev/stdin"),
+        _ => fs::metadata(name),
+    };
+    match (metadata(filename), metadata(other)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+// Without inode numbers, the canonical paths are compared, and stdin
+// can't be told apart from a file
+#[cfg(not(unix))]
+pub fn same_file(filename: &str, other: &str) -> bool {
+    match (fs::canonicalize(filename), fs::canonicalize(other)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{same_file, Output};
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn test_output_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.txt");
+        let second = dir.path().join("second.txt");
+        fs::write(&second, "old\n").unwrap();
+
+        let mut output = Output::new();
+        output.add_file(first.to_str().unwrap()).unwrap();
+        output.add_file(second.to_str().unwrap()).unwrap();
+        writeln!(output, "new").unwrap();
+        // nothing is replaced until the output is finished
+        assert!(!first.exists());
+        assert_eq!(fs::read_to_string(&second).unwrap(), "old\n");
+
+        output.finish().unwrap();
+        assert_eq!(fs::read_to_string(&first).unwrap(), "new\n");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "new\n");
+        // no temporary files are left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_output_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        fs::write(&file, "old\n").unwrap();
+
+        let mut output = Output::new();
+        output.add_file(file.to_str().unwrap()).unwrap();
+        writeln!(output, "new").unwrap();
+        drop(output);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old\n");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_same_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        let link = dir.path().join("link.txt");
+        let other = dir.path().join("other.txt");
+        fs::write(&file, "").unwrap();
+        fs::write(&other, "").unwrap();
+        fs::hard_link(&file, &link).unwrap();
+
+        let name = |path: &std::path::Path| path.to_str().unwrap().to_string();
+        assert!(same_file(&name(&file), &name(&file)));
+        assert!(same_file(&name(&file), &name(&link)));
+        assert!(!same_file(&name(&file), &name(&other)));
+        assert!(!same_file(&name(&file), &name(&dir.path().join("missing"))));
+    }
+}
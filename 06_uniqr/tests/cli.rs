@@ -2,6 +2,8 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
 use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
 use tempfile::NamedTempFile;
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
@@ -13,6 +15,13 @@ struct Test {
 }
 
 const PRG: &str = "uniqr";
+const CASE: &str = "tests/inputs/case.txt";
+const LOG: &str = "tests/inputs/log.txt";
+const GLOBAL: &str = "tests/inputs/global.txt";
+const ROWS: &str = "tests/inputs/rows.csv";
+const CRLF: &str = "tests/inputs/crlf.txt";
+const SPACES: &str = "tests/inputs/spaces.txt";
+const NO_NEWLINE: &str = "tests/inputs/no_newline.txt";
 
 const EMPTY: Test = Test {
     input: "tests/inputs/empty.txt",
@@ -108,8 +117,32 @@ fn dies_bad_file() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_bad_all_repeated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--all-repeated=sometimes", THREE.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "'sometimes' isn't a valid value for '--all-repeated=<METHOD>'",
+        ));
+    Ok(())
+}
+
 // --------------------------------------------------
 // HELPER FUNCTIONS
+fn run_args(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read_to_string(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
 fn run(test: &Test) -> TestResult {
     let expected = fs::read_to_string(test.out)?;
     Command::cargo_bin(PRG)?
@@ -124,7 +157,7 @@ fn run(test: &Test) -> TestResult {
 fn run_count(test: &Test) -> TestResult {
     let expected = fs::read_to_string(test.out_count)?;
     Command::cargo_bin(PRG)?
-        .args(&[test.input, "-c"])
+        .args([test.input, "-c"])
         .assert()
         .success()
         .stdout(expected);
@@ -162,12 +195,12 @@ fn run_outfile(test: &Test) -> TestResult {
     let outfile = NamedTempFile::new()?;
     let outpath = &outfile.path().to_str().unwrap();
     Command::cargo_bin(PRG)?
-        .args(&[test.input, outpath])
+        .args([test.input, outpath])
         .assert()
         .success()
         .stdout("");
 
-    let contents = fs::read_to_string(&outpath)?;
+    let contents = fs::read_to_string(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -179,13 +212,13 @@ fn run_outfile_count(test: &Test) -> TestResult {
     let outpath = &outfile.path().to_str().unwrap();
 
     Command::cargo_bin(PRG)?
-        .args(&[test.input, outpath, "--count"])
+        .args([test.input, outpath, "--count"])
         .assert()
         .success()
         .stdout("");
 
     let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(&outpath)?;
+    let contents = fs::read_to_string(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -198,13 +231,13 @@ fn run_stdin_outfile_count(test: &Test) -> TestResult {
     let outpath = &outfile.path().to_str().unwrap();
 
     Command::cargo_bin(PRG)?
-        .args(&["-", outpath, "-c"])
+        .args(["-", outpath, "-c"])
         .write_stdin(input)
         .assert()
         .stdout("");
 
     let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(&outpath)?;
+    let contents = fs::read_to_string(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -605,3 +638,622 @@ fn t6_outfile_count() -> TestResult {
 fn t6_stdin_outfile_count() -> TestResult {
     run_stdin_outfile_count(&T6)
 }
+
+// --------------------------------------------------
+#[test]
+fn three_repeated() -> TestResult {
+    run_args(&["-d", THREE.input], "tests/expected/three.txt.d.out")
+}
+
+#[test]
+fn three_repeated_count() -> TestResult {
+    run_args(
+        &["-d", "-c", THREE.input],
+        "tests/expected/three.txt.cd.out",
+    )
+}
+
+#[test]
+fn three_all_repeated() -> TestResult {
+    run_args(&["-D", THREE.input], "tests/expected/three.txt.D.out")
+}
+
+#[test]
+fn three_all_repeated_count() -> TestResult {
+    run_args(
+        &["-D", "-c", THREE.input],
+        "tests/expected/three.txt.cD.out",
+    )
+}
+
+#[test]
+fn three_all_repeated_prepend() -> TestResult {
+    run_args(
+        &["--all-repeated=prepend", THREE.input],
+        "tests/expected/three.txt.D-prepend.out",
+    )
+}
+
+#[test]
+fn three_all_repeated_separate() -> TestResult {
+    run_args(
+        &["--all-repeated=separate", THREE.input],
+        "tests/expected/three.txt.D-separate.out",
+    )
+}
+
+#[test]
+fn three_unique() -> TestResult {
+    run_args(&["-u", THREE.input], "tests/expected/three.txt.u.out")
+}
+
+#[test]
+fn three_unique_count() -> TestResult {
+    run_args(
+        &["--unique", "--count", THREE.input],
+        "tests/expected/three.txt.cu.out",
+    )
+}
+
+#[test]
+fn three_repeated_unique() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-d", "-u", THREE.input])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn case_ignore_case() -> TestResult {
+    run_args(&["-i", CASE], "tests/expected/case.txt.i.out")
+}
+
+#[test]
+fn case_ignore_case_count() -> TestResult {
+    run_args(
+        &["--ignore-case", "-c", CASE],
+        "tests/expected/case.txt.ci.out",
+    )
+}
+
+#[test]
+fn case_ignore_case_repeated() -> TestResult {
+    run_args(&["-i", "-d", CASE], "tests/expected/case.txt.id.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_skip_fields() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "x", LOG])
+        .assert()
+        .failure()
+        .stderr("illegal field count -- x\n");
+    Ok(())
+}
+
+#[test]
+fn dies_bad_check_chars() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-w", "2.5", LOG])
+        .assert()
+        .failure()
+        .stderr("illegal char count -- 2.5\n");
+    Ok(())
+}
+
+#[test]
+fn log_skip_fields() -> TestResult {
+    run_args(&["-f", "1", LOG], "tests/expected/log.txt.f1.out")
+}
+
+#[test]
+fn log_skip_fields_count() -> TestResult {
+    run_args(
+        &["-c", "--skip-fields=1", LOG],
+        "tests/expected/log.txt.cf1.out",
+    )
+}
+
+#[test]
+fn log_skip_chars() -> TestResult {
+    run_args(&["-s", "6", LOG], "tests/expected/log.txt.s6.out")
+}
+
+#[test]
+fn log_skip_chars_ignore_case() -> TestResult {
+    run_args(&["-i", "-s", "6", LOG], "tests/expected/log.txt.is6.out")
+}
+
+#[test]
+fn log_check_chars() -> TestResult {
+    run_args(&["-w", "3", LOG], "tests/expected/log.txt.w3.out")
+}
+
+#[test]
+fn log_skip_all_fields() -> TestResult {
+    run_args(&["-f", "9", LOG], "tests/expected/log.txt.f9.out")
+}
+
+#[test]
+fn log_fields_chars_and_check() -> TestResult {
+    run_args(
+        &["-f", "1", "-s", "1", "-w", "4", LOG],
+        "tests/expected/log.txt.f1s1w4.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_global_and_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--global", "-c", GLOBAL])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_max_memory() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--global", "--max-memory", "1Q", GLOBAL])
+        .assert()
+        .failure()
+        .stderr("illegal memory size -- 1Q\n");
+    Ok(())
+}
+
+#[test]
+fn dies_max_memory_exceeded() -> TestResult {
+    let input: String = (0..1000).map(|n| format!("{}\n", n)).collect();
+    Command::cargo_bin(PRG)?
+        .args(["--global", "--max-memory=1K"])
+        .write_stdin(input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("memory limit exceeded after"));
+    Ok(())
+}
+
+#[test]
+fn global() -> TestResult {
+    run_args(&["--global", GLOBAL], "tests/expected/global.txt.out")
+}
+
+#[test]
+fn global_exact() -> TestResult {
+    run_args(&["--global=exact", GLOBAL], "tests/expected/global.txt.out")
+}
+
+#[test]
+fn global_bloom() -> TestResult {
+    run_args(
+        &["--global=bloom", "--max-memory", "4K", GLOBAL],
+        "tests/expected/global.txt.out",
+    )
+}
+
+#[test]
+fn global_ignore_case() -> TestResult {
+    run_args(
+        &["--global", "-i", GLOBAL],
+        "tests/expected/global.txt.i.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_group_and_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--group", "-c", THREE.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn three_group() -> TestResult {
+    run_args(
+        &["--group", THREE.input],
+        "tests/expected/three.txt.group-separate.out",
+    )
+}
+
+#[test]
+fn three_group_separate() -> TestResult {
+    run_args(
+        &["--group=separate", THREE.input],
+        "tests/expected/three.txt.group-separate.out",
+    )
+}
+
+#[test]
+fn three_group_prepend() -> TestResult {
+    run_args(
+        &["--group=prepend", THREE.input],
+        "tests/expected/three.txt.group-prepend.out",
+    )
+}
+
+#[test]
+fn three_group_append() -> TestResult {
+    run_args(
+        &["--group=append", THREE.input],
+        "tests/expected/three.txt.group-append.out",
+    )
+}
+
+#[test]
+fn three_group_both() -> TestResult {
+    run_args(
+        &["--group=both", THREE.input],
+        "tests/expected/three.txt.group-both.out",
+    )
+}
+
+#[test]
+fn empty_group_both() -> TestResult {
+    run_args(&["--group=both", EMPTY.input], EMPTY.out)
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_key() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--key", "0", ROWS])
+        .assert()
+        .failure()
+        .stderr("illegal list value: \"0\"\n");
+    Ok(())
+}
+
+#[test]
+fn dies_bad_delimiter() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1", "--delimiter", ",,", ROWS])
+        .assert()
+        .failure()
+        .stderr("--delimiter \",,\" must be a single byte\n");
+    Ok(())
+}
+
+#[test]
+fn rows_key() -> TestResult {
+    run_args(
+        &["--key", "1", "--delimiter", ",", ROWS],
+        "tests/expected/rows.csv.key1.out",
+    )
+}
+
+#[test]
+fn rows_key_ranges() -> TestResult {
+    // the same lists as "1" in cutr's syntax
+    for list in ["1-1", "-1"] {
+        run_args(
+            &["--key", list, "--delimiter", ",", ROWS],
+            "tests/expected/rows.csv.key1.out",
+        )?;
+    }
+    Command::cargo_bin(PRG)?
+        .args(["--key", "2-", "--delimiter", ","])
+        .write_stdin("1,a,b\n2,a,b\n3,a,c\n")
+        .assert()
+        .success()
+        .stdout("1,a,b\n3,a,c\n");
+    Ok(())
+}
+
+#[test]
+fn rows_key_quoted_delimiter() -> TestResult {
+    // the same fields joined with the delimiter, but not the same fields
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1-2", "--delimiter", ","])
+        .write_stdin("\"a,b\",c\na,\"b,c\"\n")
+        .assert()
+        .success()
+        .stdout("\"a,b\",c\na,\"b,c\"\n");
+    Ok(())
+}
+
+#[test]
+fn rows_key_crlf() -> TestResult {
+    // as with whole lines, the CR is part of the key
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1-2", "--delimiter", ","])
+        .write_stdin("x,1\r\nx,1\n")
+        .assert()
+        .success()
+        .stdout("x,1\r\nx,1\n");
+    Command::cargo_bin(PRG)?
+        .args([
+            "--key",
+            "1-2",
+            "--delimiter",
+            ",",
+            "--ignore-trailing-space",
+        ])
+        .write_stdin("x,1\r\nx,1\n")
+        .assert()
+        .success()
+        .stdout("x,1\r\n");
+    Ok(())
+}
+
+#[test]
+fn rows_key_count() -> TestResult {
+    run_args(
+        &["-c", "--key", "1", "--delimiter", ",", ROWS],
+        "tests/expected/rows.csv.ckey1.out",
+    )
+}
+
+#[test]
+fn rows_key_check_chars() -> TestResult {
+    run_args(
+        &["--key", "3", "--delimiter", ",", "-w", "1", ROWS],
+        "tests/expected/rows.csv.key3-w1.out",
+    )
+}
+
+#[test]
+fn rows_key_global() -> TestResult {
+    run_args(
+        &["--global", "--key", "1,3", "--delimiter", ",", ROWS],
+        "tests/expected/rows.csv.global-key1-3.out",
+    )
+}
+
+// --------------------------------------------------
+// as in GNU uniq, a last line without a newline is printed with one
+#[test]
+fn no_newline() -> TestResult {
+    run_args(&[NO_NEWLINE], "tests/expected/no_newline.txt.out")
+}
+
+#[test]
+fn no_newline_count() -> TestResult {
+    run_args(&["-c", NO_NEWLINE], "tests/expected/no_newline.txt.c.out")
+}
+
+#[test]
+fn no_newline_group_append() -> TestResult {
+    run_args(
+        &["--group=append", NO_NEWLINE],
+        "tests/expected/no_newline.txt.group-append.out",
+    )
+}
+
+#[test]
+fn no_newline_global() -> TestResult {
+    run_args(
+        &["--global", NO_NEWLINE],
+        "tests/expected/no_newline.txt.global.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn crlf() -> TestResult {
+    run_args(&[CRLF], "tests/expected/crlf.txt.out")
+}
+
+#[test]
+fn crlf_count() -> TestResult {
+    run_args(&["-c", CRLF], "tests/expected/crlf.txt.c.out")
+}
+
+#[test]
+fn crlf_differs_from_lf() -> TestResult {
+    // as in GNU uniq, the CR is part of the line
+    Command::cargo_bin(PRG)?
+        .write_stdin("a\r\na\n")
+        .assert()
+        .success()
+        .stdout("a\r\na\n");
+    Command::cargo_bin(PRG)?
+        .args(["--ignore-trailing-space"])
+        .write_stdin("a\r\na\n")
+        .assert()
+        .success()
+        .stdout("a\r\n");
+    Ok(())
+}
+
+#[test]
+fn crlf_ignore_trailing_space() -> TestResult {
+    run_args(
+        &["--ignore-trailing-space", CRLF],
+        "tests/expected/crlf.txt.trailing.out",
+    )
+}
+
+#[test]
+fn spaces() -> TestResult {
+    run_args(&[SPACES], "tests/expected/spaces.txt.out")
+}
+
+#[test]
+fn spaces_count() -> TestResult {
+    run_args(&["-c", SPACES], "tests/expected/spaces.txt.c.out")
+}
+
+#[test]
+fn spaces_ignore_trailing_space() -> TestResult {
+    run_args(
+        &["--ignore-trailing-space", SPACES],
+        "tests/expected/spaces.txt.trailing.out",
+    )
+}
+
+#[test]
+fn global_ignore_trailing_space() -> TestResult {
+    run_args(
+        &["--global", "--ignore-trailing-space", GLOBAL],
+        "tests/expected/global.txt.trailing.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_same_file() -> TestResult {
+    let file = NamedTempFile::new()?;
+    let path = file.path().to_str().unwrap();
+    fs::copy(THREE.input, path)?;
+    Command::cargo_bin(PRG)?
+        .args([path, path])
+        .assert()
+        .failure()
+        .stderr(format!("{}: input and output are the same file\n", path));
+
+    // the input is left alone
+    assert_eq!(fs::read_to_string(path)?, fs::read_to_string(THREE.input)?);
+    Ok(())
+}
+
+#[test]
+fn dies_tee_same_file() -> TestResult {
+    let file = NamedTempFile::new()?;
+    let path = file.path().to_str().unwrap();
+    fs::copy(THREE.input, path)?;
+    Command::cargo_bin(PRG)?
+        .args(["--tee", path, path])
+        .assert()
+        .failure()
+        .stderr(format!("{}: input and output are the same file\n", path));
+    assert_eq!(fs::read_to_string(path)?, fs::read_to_string(THREE.input)?);
+    Ok(())
+}
+
+#[test]
+fn failed_run_keeps_outfile() -> TestResult {
+    let outfile = NamedTempFile::new()?;
+    let outpath = outfile.path().to_str().unwrap();
+    fs::write(outpath, "old\n")?;
+    let input: String = (0..1000).map(|n| format!("{}\n", n)).collect();
+    Command::cargo_bin(PRG)?
+        .args(["--global", "--max-memory=1K", "-", outpath])
+        .write_stdin(input)
+        .assert()
+        .failure();
+    assert_eq!(fs::read_to_string(outpath)?, "old\n");
+    Ok(())
+}
+
+#[test]
+fn three_tee() -> TestResult {
+    let expected = fs::read_to_string(THREE.out_count)?;
+    let teefile = NamedTempFile::new()?;
+    let teepath = teefile.path().to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--tee", teepath, THREE.input])
+        .assert()
+        .success()
+        .stdout(expected.clone());
+    assert_eq!(fs::read_to_string(teepath)?, expected);
+    Ok(())
+}
+
+#[test]
+fn three_tee_outfile() -> TestResult {
+    let expected = fs::read_to_string(THREE.out)?;
+    let outfile = NamedTempFile::new()?;
+    let outpath = outfile.path().to_str().unwrap();
+    let teefile = NamedTempFile::new()?;
+    let teepath = teefile.path().to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, outpath, "--tee", teepath])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(fs::read_to_string(outpath)?, expected);
+    assert_eq!(fs::read_to_string(teepath)?, expected);
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn outfile_fifo() -> TestResult {
+    let expected = fs::read_to_string(THREE.out)?;
+    let dir = tempfile::tempdir()?;
+    let fifo = dir.path().join("fifo");
+    assert!(std::process::Command::new("mkfifo")
+        .arg(&fifo)
+        .status()?
+        .success());
+    let reader = {
+        let fifo = fifo.clone();
+        std::thread::spawn(move || fs::read_to_string(fifo))
+    };
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, fifo.to_str().unwrap()])
+        .assert()
+        .success();
+    // the reader gets the output and the FIFO is left in place
+    assert_eq!(reader.join().unwrap()?, expected);
+    assert!(fs::symlink_metadata(&fifo)?.file_type().is_fifo());
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn outfile_symlink() -> TestResult {
+    let expected = fs::read_to_string(THREE.out)?;
+    let dir = tempfile::tempdir()?;
+    let target = dir.path().join("target.txt");
+    let link = dir.path().join("link.txt");
+    fs::write(&target, "old\n")?;
+    std::os::unix::fs::symlink(&target, &link)?;
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, link.to_str().unwrap()])
+        .assert()
+        .success();
+    // the file the link points to is replaced, not the link
+    assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+    assert_eq!(fs::read_to_string(&target)?, expected);
+    assert_eq!(fs::read_dir(dir.path())?.count(), 2);
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn outfile_keeps_mode() -> TestResult {
+    let expected = fs::read_to_string(THREE.out)?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("private.txt");
+    for mode in [0o600, 0o664] {
+        fs::write(&path, "old\n")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+        Command::cargo_bin(PRG)?
+            .args([THREE.input, path.to_str().unwrap()])
+            .assert()
+            .success();
+        assert_eq!(fs::read_to_string(&path)?, expected);
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, mode);
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn dies_read_only_outfile() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("read-only.txt");
+    fs::write(&path, "old\n")?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o444))?;
+    // root may write to any file, so there is nothing to test
+    if fs::OpenOptions::new().write(true).open(&path).is_ok() {
+        return Ok(());
+    }
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Permission denied"));
+    assert_eq!(fs::read_to_string(&path)?, "old\n");
+    Ok(())
+}
@@ -39,3 +39,22 @@ find "$IN_DIR/a" "$IN_DIR/d" -name \*.txt > "$OUT_DIR/name_txt_path_a_d.txt"
 find "$IN_DIR" -name a* > "$OUT_DIR/name_a.txt"
 find "$IN_DIR" -type f -name a* > "$OUT_DIR/type_f_name_a.txt"
 find "$IN_DIR" -type d -name a* > "$OUT_DIR/type_d_name_a.txt"
+
+find "$IN_DIR" -type f -size 2c > "$OUT_DIR/type_f_size_2c.txt"
+find "$IN_DIR" -size -1k > "$OUT_DIR/size_less_1k.txt"
+
+find "$IN_DIR" ! -type d > "$OUT_DIR/not_type_d.txt"
+find "$IN_DIR" -type f \( -name \*csv\* -o -name \*mp3\* \) > "$OUT_DIR/type_f_csv_or_mp3.txt"
+find "$IN_DIR" -type f ! \( -name \*txt\* -o -name \*csv\* \) > "$OUT_DIR/type_f_not_txt_csv.txt"
+find "$IN_DIR" -name \*a\* -type d -o -name \*.tsv > "$OUT_DIR/name_a_dir_or_tsv.txt"
+
+find "$IN_DIR" -maxdepth 1 > "$OUT_DIR/maxdepth_1.txt"
+find "$IN_DIR" -mindepth 2 -maxdepth 3 -type f > "$OUT_DIR/type_f_depth_2_3.txt"
+find "$IN_DIR" -name a -prune -o -type f > "$OUT_DIR/prune_a.txt"
+find -L "$IN_DIR" -type f > "$OUT_DIR/follow_type_f.txt"
+
+find "$IN_DIR" -iname \*.C[sS]V > "$OUT_DIR/iname_csv.txt"
+find "$IN_DIR" -path \*/b/\* > "$OUT_DIR/path_glob_b.txt"
+find "$IN_DIR" -regextype posix-extended -regex '.*/[a-d]\.txt' > "$OUT_DIR/regex_txt.txt"
+find "$IN_DIR" -regextype posix-basic -iregex '.*/\(A\|D\)\.T.*' > "$OUT_DIR/regex_basic.txt"
+find "$IN_DIR" -type d,l > "$OUT_DIR/type_d_l.txt"
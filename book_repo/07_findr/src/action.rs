@@ -0,0 +1,458 @@
+use crate::{entry::Entry, MyResult};
+use chrono::{DateTime, Local};
+use std::{
+    collections::BTreeMap,
+    ffi::{OsStr, OsString},
+    fs,
+    io::{self, BufRead, BufWriter, IsTerminal, Write},
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+    process,
+};
+use users::get_user_by_uid;
+
+/// The most bytes of file names given to one command by -exec ... +,
+/// well under the limit on the arguments of a command
+const MAX_BATCH_BYTES: usize = 128 * 1024;
+
+// --------------------------------------------------
+/// Something done with an entry rather than a test of it
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    /// the path followed by a newline
+    Print,
+    /// the path followed by a null byte
+    Print0,
+    Printf(Vec<Format>),
+    Exec(Exec),
+    /// removes the file or the empty directory
+    Delete,
+}
+
+// --------------------------------------------------
+/// A command run by -exec, -execdir, -ok or -okdir
+#[derive(Debug, PartialEq)]
+pub struct Exec {
+    /// the command and its arguments, where {} stands for the path
+    pub command: Vec<String>,
+    /// run in the directory of the entry, with {} as ./name
+    pub in_dir: bool,
+    /// ask on stderr before running the command
+    pub prompt: bool,
+    /// the number of the batch of an -exec ... +, which gives the
+    /// paths to the command many at a time once the walk is done
+    pub batch: Option<usize>,
+}
+
+// --------------------------------------------------
+/// A piece of the format of -printf
+#[derive(Debug, PartialEq)]
+pub enum Format {
+    Text(String),
+    /// %p, the path
+    Path,
+    /// %f, the file name
+    Name,
+    /// %s, the size in bytes
+    Size,
+    /// %m, the permission bits in octal
+    Mode,
+    /// %u, the user name or ID
+    User,
+    /// %t, the modification time as ctime(3) gives it
+    Time,
+}
+
+// --------------------------------------------------
+/// The paths waiting for the command of an -exec ... +
+struct Batch {
+    command: Vec<String>,
+    /// the directory the paths are in, for -execdir ... +
+    dir: Option<PathBuf>,
+    args: Vec<OsString>,
+    bytes: usize,
+}
+
+// --------------------------------------------------
+/// Where the actions print
+enum Output {
+    Stdout(BufWriter<io::Stdout>),
+    /// kept until taken, so a walker thread can hand it on
+    Buffer(Vec<u8>),
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::Buffer(out) => out.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::Buffer(_) => Ok(()),
+        }
+    }
+}
+
+// --------------------------------------------------
+/// What the expression shares over the whole walk: where it prints,
+/// the commands waiting to be run, and whether to skip a directory
+pub struct Context {
+    /// don't descend into the directory, reset for each entry
+    pub prune: bool,
+    /// an action failed, so findr should exit with an error
+    pub failed: bool,
+    out: Output,
+    batches: BTreeMap<usize, Batch>,
+}
+
+impl Context {
+    fn new(out: Output) -> Context {
+        Context {
+            prune: false,
+            failed: false,
+            out,
+            batches: BTreeMap::new(),
+        }
+    }
+
+    /// A context that prints to stdout, a line at a time to a
+    /// terminal so that each match shows as soon as it is found
+    pub fn stdout() -> Context {
+        let stdout = io::stdout();
+        let capacity = match stdout.is_terminal() {
+            true => 0,
+            false => 8 * 1024,
+        };
+        Context::new(Output::Stdout(BufWriter::with_capacity(capacity, stdout)))
+    }
+
+    /// A context that keeps what it prints until take_output
+    pub fn buffered() -> Context {
+        Context::new(Output::Buffer(vec![]))
+    }
+
+    /// What was printed since the last call, if buffered
+    pub fn take_output(&mut self) -> Vec<u8> {
+        match &mut self.out {
+            Output::Stdout(_) => vec![],
+            Output::Buffer(out) => std::mem::take(out),
+        }
+    }
+
+    /// Runs the commands still waiting for their paths
+    pub fn finish(&mut self) -> MyResult<()> {
+        for batch in std::mem::take(&mut self.batches).into_values() {
+            self.run_batch(batch);
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn run_batch(&mut self, batch: Batch) {
+        if batch.args.is_empty() {
+            return;
+        }
+        let mut args: Vec<OsString> =
+            batch.command.iter().map(OsString::from).collect();
+        // the {} before the + is replaced by all of the paths
+        args.pop();
+        args.extend(batch.args);
+        match self.command(&args, batch.dir.as_deref()) {
+            Ok(true) => {}
+            Ok(false) => self.failed = true,
+            Err(e) => {
+                eprintln!("{}", e);
+                self.failed = true;
+            }
+        }
+    }
+
+    /// Runs a command, returning whether it succeeded
+    fn command(
+        &mut self,
+        args: &[OsString],
+        dir: Option<&Path>,
+    ) -> MyResult<bool> {
+        // anything printed so far comes before what the command prints
+        self.out.flush()?;
+        let mut command = process::Command::new(&args[0]);
+        command.args(&args[1..]);
+        if let Some(dir) = dir {
+            command.current_dir(dir);
+        }
+        let status = command
+            .status()
+            .map_err(|e| format!("{}: {}", args[0].to_string_lossy(), e))?;
+        Ok(status.success())
+    }
+}
+
+impl Action {
+    /// Does the action, returning whether it succeeded
+    pub fn run(&self, entry: &Entry, context: &mut Context) -> MyResult<bool> {
+        let path = entry.path().as_os_str();
+        match self {
+            Action::Print => {
+                context.out.write_all(path.as_bytes())?;
+                context.out.write_all(b"\n")?;
+            }
+            Action::Print0 => {
+                context.out.write_all(path.as_bytes())?;
+                context.out.write_all(b"\0")?;
+            }
+            Action::Printf(formats) => {
+                let text = printf(formats, entry)?;
+                context.out.write_all(&text)?;
+            }
+            Action::Delete => {
+                // find never deletes the directory it was started in
+                if path == "." {
+                    return Ok(true);
+                }
+                let removed = if entry.file_type().is_dir() {
+                    fs::remove_dir(path)
+                } else {
+                    fs::remove_file(path)
+                };
+                if let Err(e) = removed {
+                    context.failed = true;
+                    return Err(
+                        format!("{}: {}", entry.path().display(), e).into()
+                    );
+                }
+            }
+            Action::Exec(exec) => return exec.run(entry, context),
+        }
+        Ok(true)
+    }
+}
+
+impl Exec {
+    fn run(&self, entry: &Entry, context: &mut Context) -> MyResult<bool> {
+        let (dir, path) = if self.in_dir {
+            let (dir, name) = split_dir(entry.path());
+            (Some(dir), name)
+        } else {
+            (None, entry.path().as_os_str().to_os_string())
+        };
+
+        if let Some(id) = self.batch {
+            let bytes = path.len() + 1;
+            let full = context.batches.get(&id).is_some_and(|batch| {
+                batch.dir != dir || batch.bytes + bytes > MAX_BATCH_BYTES
+            });
+            if full {
+                let batch = context.batches.remove(&id).unwrap();
+                context.run_batch(batch);
+            }
+            let batch = context.batches.entry(id).or_insert_with(|| Batch {
+                command: self.command.clone(),
+                dir,
+                args: vec![],
+                bytes: 0,
+            });
+            batch.args.push(path);
+            batch.bytes += bytes;
+            return Ok(true);
+        }
+
+        let args = command_args(&self.command, &path);
+        if self.prompt && !confirm(&args, context)? {
+            return Ok(false);
+        }
+        context.command(&args, dir.as_deref())
+    }
+}
+
+// --------------------------------------------------
+/// The directory of a path and its name as ./name within it, as
+/// -execdir gives them
+fn split_dir(path: &Path) -> (PathBuf, OsString) {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = match path.file_name() {
+        Some(name) => Path::new(".").join(name).into_os_string(),
+        None => path.as_os_str().to_os_string(),
+    };
+    (dir.to_path_buf(), name)
+}
+
+// --------------------------------------------------
+/// The arguments of a command with each {} replaced by the path
+fn command_args(command: &[String], path: &OsStr) -> Vec<OsString> {
+    command
+        .iter()
+        .map(|arg| {
+            if arg == "{}" {
+                path.to_os_string()
+            } else {
+                arg.replace("{}", &path.to_string_lossy()).into()
+            }
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+/// Asks on stderr whether to run a command, reading the answer from
+/// stdin, where anything starting with y is a yes
+fn confirm(args: &[OsString], context: &mut Context) -> MyResult<bool> {
+    context.out.flush()?;
+    let command: Vec<_> =
+        args.iter().map(|arg| arg.to_string_lossy()).collect();
+    eprint!("< {} > ? ", command.join(" "));
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim_start().starts_with(['y', 'Y']))
+}
+
+// --------------------------------------------------
+fn printf(formats: &[Format], entry: &Entry) -> MyResult<Vec<u8>> {
+    let mut text = vec![];
+    for format in formats {
+        match format {
+            Format::Text(s) => text.extend_from_slice(s.as_bytes()),
+            Format::Path => {
+                text.extend_from_slice(entry.path().as_os_str().as_bytes())
+            }
+            Format::Name => {
+                text.extend_from_slice(entry.file_name().as_bytes())
+            }
+            Format::Size | Format::Mode | Format::User | Format::Time => {
+                let metadata = entry.metadata().map_err(|e| {
+                    format!("{}: {}", entry.path().display(), e)
+                })?;
+                let value = match format {
+                    Format::Size => metadata.len().to_string(),
+                    Format::Mode => format!("{:o}", metadata.mode() & 0o7777),
+                    Format::User => match get_user_by_uid(metadata.uid()) {
+                        Some(user) => user.name().to_string_lossy().to_string(),
+                        None => metadata.uid().to_string(),
+                    },
+                    _ => {
+                        let modified: DateTime<Local> =
+                            DateTime::from(metadata.modified()?);
+                        modified.format("%a %b %e %H:%M:%S %Y").to_string()
+                    }
+                };
+                text.extend_from_slice(value.as_bytes());
+            }
+        }
+    }
+    Ok(text)
+}
+
+// --------------------------------------------------
+/// Parses the format of -printf, with the directives %p %f %s %m %u
+/// %t and %%, and the escapes \n \t \0 and \\
+pub fn parse_printf(value: &str) -> Option<Vec<Format>> {
+    let mut formats = vec![];
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        let format = match c {
+            '\\' => {
+                text.push(match chars.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    '0' => '\0',
+                    '\\' => '\\',
+                    _ => return None,
+                });
+                continue;
+            }
+            '%' => match chars.next()? {
+                '%' => {
+                    text.push('%');
+                    continue;
+                }
+                'p' => Format::Path,
+                'f' => Format::Name,
+                's' => Format::Size,
+                'm' => Format::Mode,
+                'u' => Format::User,
+                't' => Format::Time,
+                _ => return None,
+            },
+            _ => {
+                text.push(c);
+                continue;
+            }
+        };
+        if !text.is_empty() {
+            formats.push(Format::Text(std::mem::take(&mut text)));
+        }
+        formats.push(format);
+    }
+    if !text.is_empty() {
+        formats.push(Format::Text(text));
+    }
+    Some(formats)
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{command_args, parse_printf, split_dir, Format};
+    use std::{ffi::OsStr, path::Path};
+
+    #[test]
+    fn test_parse_printf() {
+        assert_eq!(parse_printf(""), Some(vec![]));
+        assert_eq!(
+            parse_printf("%p\\n"),
+            Some(vec![Format::Path, Format::Text("\n".to_string())])
+        );
+        assert_eq!(
+            parse_printf("%f %s%%\\t%m %u %t\\0"),
+            Some(vec![
+                Format::Name,
+                Format::Text(" ".to_string()),
+                Format::Size,
+                Format::Text("%\t".to_string()),
+                Format::Mode,
+                Format::Text(" ".to_string()),
+                Format::User,
+                Format::Text(" ".to_string()),
+                Format::Time,
+                Format::Text("\0".to_string()),
+            ])
+        );
+        for bad in ["%", "%x", "\\", "\\q", "a%"] {
+            assert_eq!(parse_printf(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_command_args() {
+        let command: Vec<String> = ["echo", "{}", "x{}y", "z"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            command_args(&command, OsStr::new("a/b")),
+            ["echo", "a/b", "xa/by", "z"]
+        );
+    }
+
+    #[test]
+    fn test_split_dir() {
+        assert_eq!(
+            split_dir(Path::new("a/b/c.txt")),
+            (Path::new("a/b").to_path_buf(), "./c.txt".into())
+        );
+        assert_eq!(
+            split_dir(Path::new("a")),
+            (Path::new(".").to_path_buf(), "./a".into())
+        );
+        assert_eq!(
+            split_dir(Path::new(".")),
+            (Path::new(".").to_path_buf(), ".".into())
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::MyResult;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, Read, Write},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+/// How many bytes from the start of a file the partial hash reads
+const PARTIAL_BYTES: u64 = 4096;
+
+// --------------------------------------------------
+/// The hash of the whole contents of the files
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hash {
+    Blake3,
+    Sha256,
+}
+
+// --------------------------------------------------
+/// What is done with the files that duplicate the first of a set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolve {
+    /// replace them with hard links to the first
+    Hardlink,
+    Delete,
+}
+
+// --------------------------------------------------
+/// The options of --duplicates
+#[derive(Debug, PartialEq)]
+pub struct Duplicates {
+    pub hash: Hash,
+    pub json: bool,
+    pub resolve: Option<Resolve>,
+    /// only print what would be done
+    pub dry_run: bool,
+}
+
+// --------------------------------------------------
+/// Files with the same contents, in order of path
+#[derive(Debug, PartialEq)]
+pub struct DuplicateSet {
+    pub size: u64,
+    pub hash: String,
+    pub files: Vec<PathBuf>,
+}
+
+// --------------------------------------------------
+/// Finds the files that have the same contents. The files are
+/// grouped by size, then by a hash of their start, and only then
+/// by a hash of their whole contents, so that most files are never
+/// read at all. Names of the same file, such as hard links, count
+/// once, and empty files are left out. A file that can't be read is
+/// reported and left out.
+pub fn find(paths: Vec<PathBuf>, hash: Hash) -> Vec<DuplicateSet> {
+    let mut inodes = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        match fs::metadata(&path) {
+            Err(e) => eprintln!("{}: {}", path.display(), e),
+            Ok(metadata) => {
+                if metadata.len() > 0
+                    && inodes.insert((metadata.dev(), metadata.ino()))
+                {
+                    by_size.entry(metadata.len()).or_default().push(path);
+                }
+            }
+        }
+    }
+
+    let mut sets = vec![];
+    for (size, paths) in by_size {
+        if paths.len() < 2 {
+            continue;
+        }
+        for (_, paths) in group_by(paths, partial_hash) {
+            for (digest, mut files) in
+                group_by(paths, |path| full_hash(path, hash))
+            {
+                files.sort();
+                sets.push(DuplicateSet {
+                    size,
+                    hash: digest,
+                    files,
+                });
+            }
+        }
+    }
+    sets.sort_by(|a, b| a.files.cmp(&b.files));
+    sets
+}
+
+// --------------------------------------------------
+/// The groups of two or more paths with the same key
+fn group_by<F>(paths: Vec<PathBuf>, key: F) -> Vec<(String, Vec<PathBuf>)>
+where
+    F: Fn(&Path) -> io::Result<String>,
+{
+    let mut groups: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        match key(&path) {
+            Err(e) => eprintln!("{}: {}", path.display(), e),
+            Ok(key) => groups.entry(key).or_default().push(path),
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect()
+}
+
+// --------------------------------------------------
+fn partial_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut File::open(path)?.take(PARTIAL_BYTES), &mut hasher)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+// --------------------------------------------------
+pub fn full_hash(path: &Path, hash: Hash) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    Ok(match hash {
+        Hash::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            io::copy(&mut file, &mut hasher)?;
+            hasher.finalize().to_hex().to_string()
+        }
+        Hash::Sha256 => {
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher)?;
+            format!("{:x}", hasher.finalize())
+        }
+    })
+}
+
+// --------------------------------------------------
+/// Prints the sets, as paths with a blank line between sets or as
+/// JSON, or with --hardlink or --delete-dups what is done to each
+/// duplicate. The first file of a set is the one kept. Returns
+/// whether a file couldn't be linked or deleted.
+pub fn report(
+    sets: &[DuplicateSet],
+    options: &Duplicates,
+    out: &mut impl Write,
+) -> MyResult<bool> {
+    if options.json {
+        let sets: Vec<_> = sets
+            .iter()
+            .map(|set| {
+                let files: Vec<_> = set
+                    .files
+                    .iter()
+                    .map(|path| path.to_string_lossy())
+                    .collect();
+                json!({"size": set.size, "hash": set.hash, "files": files})
+            })
+            .collect();
+        writeln!(out, "{}", json!(sets))?;
+    } else if options.resolve.is_none() {
+        for (i, set) in sets.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            for path in &set.files {
+                writeln!(out, "{}", path.display())?;
+            }
+        }
+    }
+
+    let resolve = match options.resolve {
+        Some(resolve) => resolve,
+        None => return Ok(false),
+    };
+    let mut failed = false;
+    for set in sets {
+        let kept = &set.files[0];
+        for path in &set.files[1..] {
+            if !options.json {
+                match resolve {
+                    Resolve::Hardlink => writeln!(
+                        out,
+                        "link {} => {}",
+                        path.display(),
+                        kept.display()
+                    )?,
+                    Resolve::Delete => {
+                        writeln!(out, "delete {}", path.display())?
+                    }
+                }
+            }
+            if options.dry_run {
+                continue;
+            }
+            let done = match resolve {
+                Resolve::Hardlink => hard_link(kept, path),
+                Resolve::Delete => fs::remove_file(path),
+            };
+            if let Err(e) = done {
+                eprintln!("{}: {}", path.display(), e);
+                failed = true;
+            }
+        }
+    }
+    Ok(failed)
+}
+
+// --------------------------------------------------
+/// Replaces a file with a hard link to another, through a temporary
+/// link beside it so that the file is never missing
+fn hard_link(kept: &Path, path: &Path) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_os_string();
+    temp.push(".findr-link");
+    let temp = PathBuf::from(temp);
+    fs::hard_link(kept, &temp)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{find, full_hash, DuplicateSet, Hash};
+    use std::{fs, path::PathBuf};
+
+    #[test]
+    fn test_full_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc");
+        fs::write(&path, "abc").unwrap();
+        assert_eq!(
+            full_hash(&path, Hash::Sha256).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            full_hash(&path, Hash::Blake3).unwrap(),
+            blake3::hash(b"abc").to_hex().to_string()
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        // the same start, so only the full hash tells them apart
+        let start = "x".repeat(5000);
+        fs::write(path("a"), format!("{}1", start)).unwrap();
+        fs::write(path("b"), format!("{}2", start)).unwrap();
+        fs::write(path("c"), format!("{}1", start)).unwrap();
+        fs::write(path("d"), "short").unwrap();
+        fs::write(path("e"), "short").unwrap();
+        fs::write(path("f"), "other").unwrap();
+        fs::write(path("g"), "").unwrap();
+        fs::write(path("h"), "").unwrap();
+        fs::hard_link(path("d"), path("i")).unwrap();
+
+        let paths: Vec<PathBuf> =
+            ["a", "b", "c", "d", "e", "f", "g", "h", "i", "missing"]
+                .into_iter()
+                .map(path)
+                .collect();
+        let sets = find(paths, Hash::Sha256);
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0].files, [path("a"), path("c")]);
+        assert_eq!(
+            sets[1],
+            DuplicateSet {
+                size: 5,
+                hash: full_hash(&path("d"), Hash::Sha256).unwrap(),
+                files: vec![path("d"), path("e")]
+            }
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    ffi::OsStr,
+    fs::{self, FileType, Metadata},
+    io,
+    path::{Path, PathBuf},
+};
+
+// --------------------------------------------------
+/// An entry found by either walker, walkdir's or the one of the
+/// ignore crate, which is what the expression is evaluated on
+#[derive(Debug)]
+pub struct Entry {
+    path: PathBuf,
+    file_type: FileType,
+    /// the path is a symbolic link that was followed
+    followed: bool,
+}
+
+impl Entry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The last part of the path, or all of it for a path like "."
+    pub fn file_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+
+    /// The type of the entry, or of what it links to if followed
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        match self.followed {
+            true => fs::metadata(&self.path),
+            false => fs::symlink_metadata(&self.path),
+        }
+    }
+}
+
+impl From<walkdir::DirEntry> for Entry {
+    fn from(entry: walkdir::DirEntry) -> Entry {
+        let file_type = entry.file_type();
+        Entry {
+            followed: entry.path_is_symlink() && !file_type.is_symlink(),
+            file_type,
+            path: entry.into_path(),
+        }
+    }
+}
+
+impl TryFrom<ignore::DirEntry> for Entry {
+    type Error = io::Error;
+
+    /// Fails only for stdin, which the walker makes of a path of "-"
+    fn try_from(entry: ignore::DirEntry) -> io::Result<Entry> {
+        let file_type = match entry.file_type() {
+            Some(file_type) => file_type,
+            None => fs::symlink_metadata(entry.path())?.file_type(),
+        };
+        Ok(Entry {
+            followed: entry.path_is_symlink() && !file_type.is_symlink(),
+            file_type,
+            path: entry.into_path(),
+        })
+    }
+}
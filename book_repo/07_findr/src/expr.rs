@@ -0,0 +1,710 @@
+use crate::{
+    action::{self, Action, Context, Exec},
+    entry::Entry,
+    predicate::{self, Predicate, RegexType, TimeField},
+    MyResult,
+};
+use std::time::SystemTime;
+
+/// findr's own options, which are only read before the paths
+const OPTIONS: &[&str] = &[
+    "--gitignore",
+    "-j",
+    "--threads",
+    "--sort",
+    "--duplicates",
+    "--hash",
+    "--json",
+    "--hardlink",
+    "--delete-dups",
+    "--dry-run",
+];
+
+// --------------------------------------------------
+/// An expression over the entries found, as in find(1)
+#[derive(Debug, PartialEq)]
+pub enum Expr {
+    True,
+    Test(Predicate),
+    /// always true, and a directory isn't descended into
+    Prune,
+    Action(Action),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    /// both sides are evaluated, and the value is the second one's
+    List(Box<Expr>, Box<Expr>),
+}
+
+// --------------------------------------------------
+/// The options that may appear in an expression, which don't test
+/// entries but change which entries are found
+#[derive(Debug, Default, PartialEq)]
+pub struct WalkOptions {
+    pub max_depth: Option<usize>,
+    pub min_depth: usize,
+    /// stay on the file system of each starting path
+    pub xdev: bool,
+    /// visit the contents of a directory before the directory
+    pub depth_first: bool,
+}
+
+impl Expr {
+    /// Evaluates the expression from left to right, skipping the
+    /// right side of -and and -or once the left side decides it
+    pub fn eval(&self, entry: &Entry, context: &mut Context) -> bool {
+        match self {
+            Expr::True => true,
+            Expr::Prune => {
+                context.prune = true;
+                true
+            }
+            // an entry whose metadata can't be read matches nothing
+            Expr::Test(predicate) => {
+                predicate.is_match(entry).unwrap_or_else(|e| {
+                    eprintln!("{}: {}", entry.path().display(), e);
+                    false
+                })
+            }
+            Expr::Action(action) => {
+                action.run(entry, context).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    false
+                })
+            }
+            Expr::Not(expr) => !expr.eval(entry, context),
+            Expr::And(left, right) => {
+                left.eval(entry, context) && right.eval(entry, context)
+            }
+            Expr::Or(left, right) => {
+                left.eval(entry, context) || right.eval(entry, context)
+            }
+            Expr::List(left, right) => {
+                left.eval(entry, context);
+                right.eval(entry, context)
+            }
+        }
+    }
+
+    /// Whether the expression does anything besides -prune, or else
+    /// the entries it matches are printed
+    pub fn has_action(&self) -> bool {
+        self.any_action(&|_| true)
+    }
+
+    /// Whether the expression has an -ok or -okdir
+    pub fn has_prompt(&self) -> bool {
+        self.any_action(
+            &|action| matches!(action, Action::Exec(exec) if exec.prompt),
+        )
+    }
+
+    fn any_action(&self, test: &dyn Fn(&Action) -> bool) -> bool {
+        match self {
+            Expr::Action(action) => test(action),
+            Expr::True | Expr::Test(_) | Expr::Prune => false,
+            Expr::Not(expr) => expr.any_action(test),
+            Expr::And(left, right)
+            | Expr::Or(left, right)
+            | Expr::List(left, right) => {
+                left.any_action(test) || right.any_action(test)
+            }
+        }
+    }
+}
+
+// --------------------------------------------------
+/// Whether an argument starts the expression rather than being a path
+pub fn is_expr_start(arg: &str) -> bool {
+    (arg.starts_with('-') && arg.len() > 1) || arg == "(" || arg == "!"
+}
+
+// --------------------------------------------------
+/// Parses the expression following the paths. From loosest to
+/// tightest, the operators are ",", -o, -a (which may be left out)
+/// and !, and parentheses group. The test names may be written with
+/// one dash or two, so --name is -name, and -n and -t are short
+/// for -name and -type. Options such as -maxdepth are always true
+/// wherever they appear and apply to the whole walk.
+pub fn parse(
+    args: &[String],
+    now: SystemTime,
+) -> MyResult<(Expr, WalkOptions)> {
+    if args.is_empty() {
+        return Ok((Expr::True, WalkOptions::default()));
+    }
+    let mut parser = Parser {
+        args,
+        pos: 0,
+        now,
+        options: WalkOptions::default(),
+        batches: 0,
+        regex_type: RegexType::Rust,
+    };
+    let expr = parser.parse_list()?;
+    match parser.peek() {
+        None => Ok((expr, parser.options)),
+        Some(")") => Err("Unexpected \")\"".into()),
+        Some(arg) => Err(format!("Unexpected \"{}\"", arg).into()),
+    }
+}
+
+// --------------------------------------------------
+struct Parser<'a> {
+    args: &'a [String],
+    pos: usize,
+    now: SystemTime,
+    options: WalkOptions,
+    /// the number of -exec ... + so far
+    batches: usize,
+    /// the syntax of the regexes that follow -regextype
+    regex_type: RegexType,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.args.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let arg = self.peek();
+        self.pos += 1;
+        arg
+    }
+
+    fn parse_list(&mut self) -> MyResult<Expr> {
+        let mut expr = self.parse_or()?;
+        while self.peek() == Some(",") {
+            self.next();
+            let right = self.parse_or()?;
+            expr = Expr::List(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_or(&mut self) -> MyResult<Expr> {
+        let mut expr = self.parse_and()?;
+        while let Some("-o" | "-or" | "--or") = self.peek() {
+            self.next();
+            let right = self.parse_and()?;
+            expr = Expr::Or(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> MyResult<Expr> {
+        let mut expr = self.parse_not()?;
+        loop {
+            match self.peek() {
+                Some("-a" | "-and" | "--and") => {
+                    self.next();
+                }
+                // two tests in a row are joined by an implicit -and
+                Some(arg)
+                    if !matches!(arg, ")" | "," | "-o" | "-or" | "--or") => {}
+                _ => break,
+            }
+            let right = self.parse_not()?;
+            expr = Expr::And(Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> MyResult<Expr> {
+        match self.peek() {
+            Some("!" | "-not" | "--not") => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.parse_not()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> MyResult<Expr> {
+        let prev = self.pos.checked_sub(1).map(|pos| self.args[pos].as_str());
+        let arg = match self.next() {
+            Some(arg) => arg,
+            None => {
+                return Err(match prev {
+                    Some(op) => {
+                        format!("Expected an expression after \"{}\"", op)
+                    }
+                    None => "Expected an expression".to_string(),
+                }
+                .into())
+            }
+        };
+        match arg {
+            "(" => {
+                let expr = self.parse_list()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => Err("Missing \")\"".into()),
+                }
+            }
+            ")" | "," | "-o" | "-or" | "--or" | "-a" | "-and" | "--and" => {
+                Err(format!("Expected an expression before \"{}\"", arg).into())
+            }
+            _ => self.parse_test(arg),
+        }
+    }
+
+    fn parse_test(&mut self, arg: &str) -> MyResult<Expr> {
+        let name = match arg.strip_prefix("--") {
+            Some(name) if !name.is_empty() => name,
+            _ => arg.strip_prefix('-').unwrap_or(arg),
+        };
+        if !arg.starts_with('-') || name.is_empty() {
+            return Err(
+                format!("Paths must precede expression: \"{}\"", arg).into()
+            );
+        }
+        let name = match name {
+            "n" => "name",
+            "t" => "type",
+            _ => name,
+        };
+
+        match name {
+            "prune" => Ok(Expr::Prune),
+            "xdev" | "mount" => {
+                self.options.xdev = true;
+                Ok(Expr::True)
+            }
+            "regextype" => {
+                let value = self.next().ok_or_else(|| {
+                    format!("Missing argument to \"{}\"", arg)
+                })?;
+                self.regex_type = match value {
+                    "rust" => RegexType::Rust,
+                    "posix-extended" => RegexType::PosixExtended,
+                    "posix-basic" => RegexType::PosixBasic,
+                    _ => {
+                        return Err(
+                            format!("Invalid {} \"{}\"", arg, value).into()
+                        )
+                    }
+                };
+                Ok(Expr::True)
+            }
+            "depth" => {
+                self.options.depth_first = true;
+                Ok(Expr::True)
+            }
+            "print" => Ok(Expr::Action(Action::Print)),
+            "print0" => Ok(Expr::Action(Action::Print0)),
+            // a directory can't be deleted before its contents
+            "delete" => {
+                self.options.depth_first = true;
+                Ok(Expr::Action(Action::Delete))
+            }
+            "printf" => {
+                let value = self.next().ok_or_else(|| {
+                    format!("Missing argument to \"{}\"", arg)
+                })?;
+                action::parse_printf(value)
+                    .map(|formats| Expr::Action(Action::Printf(formats)))
+                    .ok_or_else(|| {
+                        format!("Invalid {} \"{}\"", arg, value).into()
+                    })
+            }
+            "exec" | "execdir" | "ok" | "okdir" => {
+                self.parse_exec(arg, name).map(Expr::Action)
+            }
+            "maxdepth" | "mindepth" => {
+                let value = self.next().ok_or_else(|| {
+                    format!("Missing argument to \"{}\"", arg)
+                })?;
+                let depth: usize = value
+                    .parse()
+                    .map_err(|_| format!("Invalid {} \"{}\"", arg, value))?;
+                if name == "maxdepth" {
+                    self.options.max_depth = Some(depth);
+                } else {
+                    self.options.min_depth = depth;
+                }
+                Ok(Expr::True)
+            }
+            _ => self.parse_predicate(arg, name).map(Expr::Test),
+        }
+    }
+
+    /// Parses the command of -exec and the like, which ends with ";",
+    /// or with "{} +" to give the command many paths at once
+    fn parse_exec(&mut self, arg: &str, name: &str) -> MyResult<Action> {
+        let prompt = name.starts_with("ok");
+        let mut command: Vec<String> = vec![];
+        let batch = loop {
+            match self.next() {
+                None => {
+                    return Err(
+                        format!("Missing argument to \"{}\"", arg).into()
+                    )
+                }
+                Some(";") => break false,
+                Some("+")
+                    if !prompt
+                        && command.last().map(String::as_str) == Some("{}") =>
+                {
+                    break true
+                }
+                Some(value) => command.push(value.to_string()),
+            }
+        };
+        let no_command = match batch {
+            true => command.len() < 2,
+            false => command.is_empty(),
+        };
+        if no_command {
+            return Err(format!("Missing argument to \"{}\"", arg).into());
+        }
+        if batch && command.iter().filter(|value| *value == "{}").count() > 1 {
+            return Err(format!(
+                "Only one {{}} is allowed with \"{} ... +\"",
+                arg
+            )
+            .into());
+        }
+        let batch = batch.then(|| {
+            self.batches += 1;
+            self.batches - 1
+        });
+        Ok(Action::Exec(Exec {
+            command,
+            in_dir: name.ends_with("dir"),
+            prompt,
+            batch,
+        }))
+    }
+
+    fn parse_predicate(
+        &mut self,
+        arg: &str,
+        name: &str,
+    ) -> MyResult<Predicate> {
+        match name {
+            "empty" => return Ok(Predicate::Empty),
+            "name"
+            | "iname"
+            | "path"
+            | "ipath"
+            | "wholename"
+            | "iwholename"
+            | "regex"
+            | "iregex"
+            | "contains"
+            | "type"
+            | "size"
+            | "mtime"
+            | "atime"
+            | "ctime"
+            | "mmin"
+            | "amin"
+            | "cmin"
+            | "newer"
+            | "newer-than-file-hash"
+            | "perm"
+            | "user"
+            | "group" => {}
+            _ => {
+                let option = arg.split('=').next().unwrap_or(arg);
+                return Err(match OPTIONS.contains(&option) {
+                    true => format!("{} must come before the paths", option),
+                    false => format!("Unknown predicate \"{}\"", arg),
+                }
+                .into());
+            }
+        }
+        let value = self
+            .next()
+            .ok_or_else(|| format!("Missing argument to \"{}\"", arg))?;
+        let invalid = || format!("Invalid {} \"{}\"", arg, value);
+        let now = self.now;
+        let age = |field, unit| predicate::parse_age(value, field, unit, now);
+        let day = 24 * 60 * 60;
+        // the names starting with i ignore case
+        let case_sensitive = !name.starts_with('i');
+        let predicate = match name {
+            "name" | "iname" => predicate::parse_glob(value, case_sensitive)
+                .map(Predicate::Name),
+            "path" | "ipath" | "wholename" | "iwholename" => {
+                predicate::parse_glob(value, case_sensitive)
+                    .map(Predicate::Path)
+            }
+            "regex" | "iregex" => {
+                predicate::parse_regex(value, self.regex_type, case_sensitive)
+                    .map(Predicate::Regex)
+            }
+            "contains" => predicate::parse_contains(value, self.regex_type)
+                .map(Predicate::Contains),
+            "type" => predicate::parse_types(value),
+            "size" => predicate::parse_size(value),
+            "mtime" => age(TimeField::Modified, day),
+            "atime" => age(TimeField::Accessed, day),
+            "ctime" => age(TimeField::Changed, day),
+            "mmin" => age(TimeField::Modified, 60),
+            "amin" => age(TimeField::Accessed, 60),
+            "cmin" => age(TimeField::Changed, 60),
+            "newer" => return predicate::parse_newer(value),
+            "newer-than-file-hash" => return predicate::parse_hashes(value),
+            "perm" => predicate::parse_perm(value),
+            "user" => predicate::parse_user(value),
+            "group" => predicate::parse_group(value),
+            _ => unreachable!("Unknown predicate"),
+        };
+        predicate.ok_or_else(|| invalid().into())
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{is_expr_start, parse, Expr, WalkOptions};
+    use crate::action::{Action, Exec, Format};
+    use crate::{
+        predicate::{self, Cmp, Predicate, RegexType},
+        EntryType, MyResult,
+    };
+    use std::time::SystemTime;
+
+    fn parse_str(args: &str) -> MyResult<Expr> {
+        let args: Vec<String> =
+            args.split_whitespace().map(String::from).collect();
+        parse(&args, SystemTime::now()).map(|(expr, _)| expr)
+    }
+
+    fn parse_options(args: &str) -> WalkOptions {
+        let args: Vec<String> =
+            args.split_whitespace().map(String::from).collect();
+        parse(&args, SystemTime::now()).unwrap().1
+    }
+
+    fn name(re: &str) -> Expr {
+        Expr::Test(Predicate::Name(predicate::parse_glob(re, true).unwrap()))
+    }
+
+    fn file_type(entry_type: EntryType) -> Expr {
+        Expr::Test(Predicate::Type(vec![entry_type]))
+    }
+
+    fn and(left: Expr, right: Expr) -> Expr {
+        Expr::And(Box::new(left), Box::new(right))
+    }
+
+    fn or(left: Expr, right: Expr) -> Expr {
+        Expr::Or(Box::new(left), Box::new(right))
+    }
+
+    fn not(expr: Expr) -> Expr {
+        Expr::Not(Box::new(expr))
+    }
+
+    #[test]
+    fn test_is_expr_start() {
+        assert!(is_expr_start("-name"));
+        assert!(is_expr_start("--name"));
+        assert!(is_expr_start("("));
+        assert!(is_expr_start("!"));
+        assert!(!is_expr_start("-"));
+        assert!(!is_expr_start("tests/inputs"));
+        assert!(!is_expr_start(")"));
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert_eq!(parse_str("").unwrap(), Expr::True);
+    }
+
+    #[test]
+    fn test_parse_tests() {
+        assert_eq!(parse_str("-name a").unwrap(), name("a"));
+        assert_eq!(parse_str("--name a").unwrap(), name("a"));
+        assert_eq!(parse_str("-n a").unwrap(), name("a"));
+        assert_eq!(parse_str("-t d").unwrap(), file_type(EntryType::Dir));
+        assert_eq!(
+            parse_str("-type c,b").unwrap(),
+            Expr::Test(Predicate::Type(vec![
+                EntryType::CharDevice,
+                EntryType::BlockDevice
+            ]))
+        );
+        assert_eq!(
+            parse_str("-size +2k").unwrap(),
+            Expr::Test(Predicate::Size {
+                cmp: Cmp::More(2),
+                unit: 1024
+            })
+        );
+        assert_eq!(parse_str("-empty").unwrap(), Expr::Test(Predicate::Empty));
+    }
+
+    #[test]
+    fn test_parse_patterns() {
+        let test = |predicate| Expr::Test(predicate);
+        assert_eq!(
+            parse_str("-iname *.CSV").unwrap(),
+            test(Predicate::Name(
+                predicate::parse_glob("*.CSV", false).unwrap()
+            ))
+        );
+        assert_eq!(
+            parse_str("-wholename a/*").unwrap(),
+            test(Predicate::Path(predicate::parse_glob("a/*", true).unwrap()))
+        );
+        assert_eq!(
+            parse_str("-regex a+ -regextype posix-basic -iregex a\\+").unwrap(),
+            and(
+                and(
+                    test(Predicate::Regex(
+                        predicate::parse_regex("a+", RegexType::Rust, true)
+                            .unwrap()
+                    )),
+                    Expr::True
+                ),
+                test(Predicate::Regex(
+                    predicate::parse_regex(
+                        "a+",
+                        RegexType::PosixExtended,
+                        false
+                    )
+                    .unwrap()
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        // -a binds tighter than -o, whether written or not
+        assert_eq!(
+            parse_str("-t f -n a -o -n b").unwrap(),
+            or(and(file_type(EntryType::File), name("a")), name("b"))
+        );
+        assert_eq!(
+            parse_str("-n a -o -t f -a -n b").unwrap(),
+            or(name("a"), and(file_type(EntryType::File), name("b")))
+        );
+        // ! binds tighter than -a
+        assert_eq!(
+            parse_str("! -n a -n b").unwrap(),
+            and(not(name("a")), name("b"))
+        );
+        assert_eq!(parse_str("-not -not -n a").unwrap(), not(not(name("a"))));
+        // parentheses group
+        assert_eq!(
+            parse_str("-t f ( -n a -o -n b )").unwrap(),
+            and(file_type(EntryType::File), or(name("a"), name("b")))
+        );
+        assert_eq!(
+            parse_str("! ( -n a -or -n b )").unwrap(),
+            not(or(name("a"), name("b")))
+        );
+        // operators of the same kind group to the left
+        assert_eq!(
+            parse_str("-n a -o -n b -o -n c").unwrap(),
+            or(or(name("a"), name("b")), name("c"))
+        );
+        // the comma is loosest of all
+        assert_eq!(
+            parse_str("-n a -o -n b , -n c").unwrap(),
+            Expr::List(Box::new(or(name("a"), name("b"))), Box::new(name("c")))
+        );
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(parse_options(""), WalkOptions::default());
+        assert_eq!(
+            parse_options("-maxdepth 2 -n a -mindepth 1 -xdev"),
+            WalkOptions {
+                max_depth: Some(2),
+                min_depth: 1,
+                xdev: true,
+                depth_first: false
+            }
+        );
+        assert!(parse_options("-delete").depth_first);
+        // options are true wherever they are
+        assert_eq!(
+            parse_str("-n a -maxdepth 1 -o -prune").unwrap(),
+            or(and(name("a"), Expr::True), Expr::Prune)
+        );
+    }
+
+    #[test]
+    fn test_parse_actions() {
+        let exec = |command: &[&str], in_dir, prompt, batch| {
+            Expr::Action(Action::Exec(Exec {
+                command: command.iter().map(|s| s.to_string()).collect(),
+                in_dir,
+                prompt,
+                batch,
+            }))
+        };
+        assert_eq!(
+            parse_str("-exec rm -f {} ;").unwrap(),
+            exec(&["rm", "-f", "{}"], false, false, None)
+        );
+        assert_eq!(
+            parse_str("-exec ls {} + -execdir wc {} +").unwrap(),
+            and(
+                exec(&["ls", "{}"], false, false, Some(0)),
+                exec(&["wc", "{}"], true, false, Some(1))
+            )
+        );
+        // a + only ends the command after a {}
+        assert_eq!(
+            parse_str("-ok echo + ;").unwrap(),
+            exec(&["echo", "+"], false, true, None)
+        );
+        assert_eq!(
+            parse_str("-n a -print0 -o -printf %p").unwrap(),
+            or(
+                and(name("a"), Expr::Action(Action::Print0)),
+                Expr::Action(Action::Printf(vec![Format::Path]))
+            )
+        );
+
+        assert!(parse_str("-print").unwrap().has_action());
+        assert!(parse_str("! ( -n a , -delete )").unwrap().has_action());
+        assert!(!parse_str("-n a -o -prune").unwrap().has_action());
+        assert!(parse_str("-print , -okdir ls ;").unwrap().has_prompt());
+        assert!(!parse_str("-exec ls ;").unwrap().has_prompt());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |args| parse_str(args).unwrap_err().to_string();
+        assert_eq!(error("-name"), "Missing argument to \"-name\"");
+        assert_eq!(error("-foo x"), "Unknown predicate \"-foo\"");
+        assert_eq!(error("--sort"), "--sort must come before the paths");
+        assert_eq!(
+            error("--threads=2"),
+            "--threads must come before the paths"
+        );
+        assert_eq!(error("-j 2"), "-j must come before the paths");
+        assert_eq!(error("-type x"), "Invalid -type \"x\"");
+        assert_eq!(error("--name [a"), "Invalid --name \"[a\"");
+        assert_eq!(error("-regex ("), "Invalid -regex \"(\"");
+        assert_eq!(error("-contains ("), "Invalid -contains \"(\"");
+        assert_eq!(error("-regextype x"), "Invalid -regextype \"x\"");
+        assert_eq!(error("-size 1T"), "Invalid -size \"1T\"");
+        assert_eq!(error("-maxdepth -1"), "Invalid -maxdepth \"-1\"");
+        assert_eq!(error("-mindepth"), "Missing argument to \"-mindepth\"");
+        assert_eq!(error("( -n a"), "Missing \")\"");
+        assert_eq!(error("-n a )"), "Unexpected \")\"");
+        assert_eq!(error("( )"), "Expected an expression before \")\"");
+        assert_eq!(error("-o -n a"), "Expected an expression before \"-o\"");
+        assert_eq!(error("-n a -o"), "Expected an expression after \"-o\"");
+        assert_eq!(error("!"), "Expected an expression after \"!\"");
+        assert_eq!(error("-n a b"), "Paths must precede expression: \"b\"");
+        assert_eq!(error("-exec ls {}"), "Missing argument to \"-exec\"");
+        assert_eq!(error("-exec ;"), "Missing argument to \"-exec\"");
+        assert_eq!(error("-exec {} +"), "Missing argument to \"-exec\"");
+        assert_eq!(
+            error("-exec cp {} {} +"),
+            "Only one {} is allowed with \"-exec ... +\""
+        );
+        assert_eq!(error("-printf %z"), "Invalid -printf \"%z\"");
+    }
+}
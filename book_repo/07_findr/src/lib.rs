@@ -1,8 +1,22 @@
-use crate::EntryType::*;
-use clap::{App, Arg};
-use regex::Regex;
-use std::error::Error;
-use walkdir::{DirEntry, WalkDir};
+// findr reads modes, owners and inodes throughout, so it only builds on
+// unix
+#[cfg(not(unix))]
+compile_error!("findr only supports unix");
+
+mod action;
+mod duplicates;
+mod entry;
+mod expr;
+mod parallel;
+mod predicate;
+
+use action::{Action, Context};
+use clap::{App, AppSettings, Arg};
+use duplicates::{Duplicates, Hash, Resolve};
+use entry::Entry;
+use expr::{Expr, WalkOptions};
+use std::{error::Error, io, path::PathBuf, time::SystemTime};
+use walkdir::WalkDir;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -11,13 +25,34 @@ enum EntryType {
     Dir,
     File,
     Link,
+    Socket,
+    Fifo,
+    BlockDevice,
+    CharDevice,
+}
+
+/// When symbolic links are followed, as chosen by -P, -H and -L
+#[derive(Debug, Eq, PartialEq)]
+enum Follow {
+    Never,
+    Roots,
+    Always,
 }
 
 #[derive(Debug)]
 pub struct Config {
     paths: Vec<String>,
-    names: Vec<Regex>,
-    entry_types: Vec<EntryType>,
+    expr: Expr,
+    options: WalkOptions,
+    follow: Follow,
+    /// skip what .gitignore and other ignore files ignore
+    gitignore: bool,
+    /// walk with the ignore crate's walker on this many threads
+    threads: Option<usize>,
+    /// print in order of path, whichever walker is used
+    sort: bool,
+    /// look for duplicates among the regular files that match
+    duplicates: Option<Duplicates>,
 }
 
 // --------------------------------------------------
@@ -26,108 +61,314 @@ pub fn get_args() -> MyResult<Config> {
         .version("0.1.0")
         .author("Ken Youens-Clark <kyclark@gmail.com>")
         .about("Rust find")
+        .usage("findr [-H|-L|-P] [OPTIONS] [PATH]... [EXPRESSION]")
+        .after_help(
+            "The expression is made of tests joined by operators, \
+             as in find(1):\n\
+             \n\
+             Tests:\n    \
+             -name GLOB   file name matches the glob (-n), \
+             also -iname\n    \
+             -path GLOB   path matches the glob, also -ipath and \
+             -wholename\n    \
+             -regex RE    whole path matches the regex, also -iregex\n    \
+             -contains RE a line of a file that isn't binary matches \
+             the regex\n    \
+             -type T      entry type, b, c, d, f, l, p or s, or a \
+             list as in f,l (-t)\n    \
+             -size N      size as [+-]N[bckwMG]\n    \
+             -mtime N     days since modified, also -atime and -ctime\n    \
+             -mmin N      minutes since modified, also -amin and -cmin\n    \
+             -newer FILE  modified more recently than FILE\n    \
+             -newer-than-file-hash FILE\n                 \
+             regular file not in FILE, the output of sha256sum, or \
+             changed\n                 \
+             since its hash there\n    \
+             -perm MODE   permissions exactly MODE, all of -MODE or \
+             any of /MODE\n    \
+             -user NAME   owned by user name or ID\n    \
+             -group NAME  owned by group name or ID\n    \
+             -empty       empty file or directory\n\
+             \n\
+             Options, which are always true:\n    \
+             -maxdepth N  descend at most N levels below the paths\n    \
+             -mindepth N  ignore entries less than N levels below\n    \
+             -xdev        stay on the file system of each path \
+             (-mount)\n    \
+             -prune       don't descend into the directory\n    \
+             -depth       visit the contents of a directory first\n    \
+             -regextype T syntax of the regexes after it, rust, \
+             posix-extended\n                 \
+             or posix-basic\n\
+             \n\
+             Actions, printing with -print when there are none:\n    \
+             -print       print the path and a newline\n    \
+             -print0      print the path and a null byte\n    \
+             -printf FMT  print with %p path, %f name, %s size, \
+             %m mode,\n                 \
+             %u user and %t modification time\n    \
+             -exec CMD ;  run CMD, where {} is the path\n    \
+             -exec CMD {} +\n                 \
+             run CMD with many paths at once\n    \
+             -execdir CMD ;\n                 \
+             run CMD in the entry's directory, also with +\n    \
+             -ok CMD ;    ask before running CMD, also -okdir\n    \
+             -delete      delete the entry, implying -depth\n\
+             \n\
+             Operators, loosest first:\n    \
+             EXPR , EXPR\n    \
+             EXPR -o EXPR\n    \
+             EXPR [-a] EXPR\n    \
+             ! EXPR\n    \
+             ( EXPR )\n\
+             \n\
+             Tests may also be written with two dashes, as in --name.",
+        )
+        .setting(AppSettings::TrailingVarArg)
+        .setting(AppSettings::AllowLeadingHyphen)
         .arg(
-            Arg::with_name("paths")
-                .value_name("PATH")
-                .help("Search paths")
-                .default_value(".")
-                .multiple(true),
+            Arg::with_name("physical")
+                .short("P")
+                .help("Never follow symbolic links [default]")
+                .overrides_with_all(&["command_line", "logical"]),
         )
         .arg(
-            Arg::with_name("names")
-                .value_name("NAME")
-                .short("n")
-                .long("name")
-                .help("Name")
-                .takes_value(true)
-                .multiple(true),
+            Arg::with_name("command_line")
+                .short("H")
+                .help("Follow symbolic links given as paths")
+                .overrides_with_all(&["physical", "logical"]),
+        )
+        .arg(
+            Arg::with_name("logical")
+                .short("L")
+                .help("Follow all symbolic links")
+                .overrides_with_all(&["physical", "command_line"]),
         )
+        .arg(Arg::with_name("gitignore").long("gitignore").help(
+            "Skip what .gitignore, .ignore and the global git \
+                     excludes ignore",
+        ))
         .arg(
-            Arg::with_name("types")
-                .value_name("TYPE")
-                .short("t")
-                .long("type")
-                .help("Entry type")
-                .possible_values(&["f", "d", "l"])
-                .multiple(true)
+            Arg::with_name("threads")
+                .short("j")
+                .long("threads")
+                .value_name("N")
+                .help("Walk with N threads")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("sort")
+                .long("sort")
+                .help("Print in order of path"),
+        )
+        .arg(
+            Arg::with_name("duplicates")
+                .long("duplicates")
+                .help("Report sets of matching files with the same contents"),
+        )
+        .arg(
+            Arg::with_name("hash")
+                .long("hash")
+                .value_name("HASH")
+                .help("Hash of the contents of duplicates [default: blake3]")
+                .possible_values(&["blake3", "sha256"])
+                .requires("duplicates"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Report duplicates as JSON")
+                .requires("duplicates"),
+        )
+        .arg(
+            Arg::with_name("hardlink")
+                .long("hardlink")
+                .help("Replace duplicates with hard links to the first")
+                .requires("duplicates"),
+        )
+        .arg(
+            Arg::with_name("delete_dups")
+                .long("delete-dups")
+                .help("Delete all but the first of each set of duplicates")
+                .requires("duplicates")
+                .conflicts_with("hardlink"),
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .help("Only print what --hardlink or --delete-dups would do"),
+        )
+        .arg(
+            Arg::with_name("args")
+                .value_name("ARG")
+                .help("Search paths followed by an expression")
+                .allow_hyphen_values(true)
+                .multiple(true),
+        )
         .get_matches();
 
-    let names = matches
-        .values_of_lossy("names")
-        .map(|vals| {
-            vals.into_iter()
-                .map(|name| {
-                    Regex::new(&name)
-                        .map_err(|_| format!("Invalid --name \"{}\"", name))
-                })
-                .collect::<Result<Vec<_>, _>>()
-        })
-        .transpose()?
-        .unwrap_or_default();
-
-    // clap should disallow anything but "d," "f," or "l"
-    let entry_types = matches
-        .values_of_lossy("types")
-        .map(|vals| {
-            vals.iter()
-                .map(|val| match val.as_str() {
-                    "d" => Dir,
-                    "f" => File,
-                    "l" => Link,
-                    _ => unreachable!("Invalid type"),
-                })
-                .collect()
+    let args = matches.values_of_lossy("args").unwrap_or_default();
+    let split = args
+        .iter()
+        .position(|arg| expr::is_expr_start(arg))
+        .unwrap_or(args.len());
+    let (paths, expr_args) = args.split_at(split);
+    let paths = if paths.is_empty() {
+        vec![".".to_string()]
+    } else {
+        paths.to_vec()
+    };
+
+    // times are measured from when findr started, as find does
+    let (mut expr, options) = expr::parse(expr_args, SystemTime::now())?;
+    let duplicates = get_duplicates(&matches)?;
+    if duplicates.is_some() {
+        // the files that match are compared rather than printed
+        if expr.has_action() {
+            return Err("Actions can't be used with --duplicates".into());
+        }
+    } else if !expr.has_action() {
+        expr = Expr::And(Box::new(expr), Box::new(Expr::Action(Action::Print)));
+    }
+
+    let follow = if matches.is_present("logical") {
+        Follow::Always
+    } else if matches.is_present("command_line") {
+        Follow::Roots
+    } else {
+        Follow::Never
+    };
+
+    let threads = matches
+        .value_of("threads")
+        .map(|value| match value.parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("Invalid --threads \"{}\"", value)),
         })
-        .unwrap_or_default();
+        .transpose()?;
+    let gitignore = matches.is_present("gitignore");
+
+    // what the ignore crate's walker can't do
+    if gitignore || threads.is_some() {
+        let unsupported = |what| -> MyResult<Config> {
+            Err(
+                format!("{} can't be used with --gitignore or --threads", what)
+                    .into(),
+            )
+        };
+        if options.depth_first {
+            return unsupported("-depth or -delete");
+        }
+        if follow == Follow::Roots {
+            return unsupported("-H");
+        }
+    }
+    // the questions of -ok would be asked all at once
+    if threads.unwrap_or(1) > 1 && expr.has_prompt() {
+        return Err("-ok can't be used with more than one thread".into());
+    }
 
     Ok(Config {
-        paths: matches.values_of_lossy("paths").unwrap(),
-        names,
-        entry_types,
+        paths,
+        expr,
+        options,
+        follow,
+        gitignore,
+        threads,
+        sort: matches.is_present("sort"),
+        duplicates,
     })
 }
 
 // --------------------------------------------------
-pub fn run(config: Config) -> MyResult<()> {
-    let type_filter = |entry: &DirEntry| {
-        config.entry_types.is_empty()
-            || config
-                .entry_types
-                .iter()
-                .any(|entry_type| match entry_type {
-                    Link => entry.file_type().is_symlink(),
-                    Dir => entry.file_type().is_dir(),
-                    File => entry.file_type().is_file(),
-                })
+fn get_duplicates(matches: &clap::ArgMatches) -> MyResult<Option<Duplicates>> {
+    let resolve = if matches.is_present("hardlink") {
+        Some(Resolve::Hardlink)
+    } else if matches.is_present("delete_dups") {
+        Some(Resolve::Delete)
+    } else {
+        None
     };
-
-    let name_filter = |entry: &DirEntry| {
-        config.names.is_empty()
-            || config
-                .names
-                .iter()
-                .any(|re| re.is_match(&entry.file_name().to_string_lossy()))
+    let dry_run = matches.is_present("dry_run");
+    if dry_run && resolve.is_none() {
+        return Err("--dry-run needs --hardlink or --delete-dups".into());
+    }
+    if !matches.is_present("duplicates") {
+        return Ok(None);
+    }
+    let hash = match matches.value_of("hash") {
+        Some("sha256") => Hash::Sha256,
+        _ => Hash::Blake3,
     };
+    Ok(Some(Duplicates {
+        hash,
+        json: matches.is_present("json"),
+        resolve,
+        dry_run,
+    }))
+}
 
+// --------------------------------------------------
+pub fn run(config: Config) -> MyResult<()> {
+    let mut context = Context::stdout();
+    // the regular files that match, with --duplicates
+    let mut found: Vec<PathBuf> = vec![];
     for path in &config.paths {
-        let entries = WalkDir::new(path)
-            .into_iter()
-            .filter_map(|e| match e {
+        if config.gitignore || config.threads.is_some() {
+            if parallel::walk(&config, path, &mut found)? {
+                context.failed = true;
+            }
+            continue;
+        }
+
+        let mut walker = WalkDir::new(path)
+            .min_depth(config.options.min_depth)
+            .max_depth(config.options.max_depth.unwrap_or(usize::MAX))
+            .same_file_system(config.options.xdev)
+            .follow_root_links(config.follow != Follow::Never)
+            .follow_links(config.follow == Follow::Always)
+            .contents_first(config.options.depth_first);
+        if config.sort {
+            walker = walker.sort_by_file_name();
+        }
+        let mut walker = walker.into_iter();
+
+        // a manual loop, as -prune needs to skip the walk ahead
+        while let Some(result) = walker.next() {
+            let entry = match result {
                 Err(e) => {
                     eprintln!("{}", e);
-                    None
+                    continue;
                 }
-                Ok(entry) => Some(entry),
-            })
-            .filter(type_filter)
-            .filter(name_filter)
-            .map(|entry| entry.path().display().to_string())
-            .collect::<Vec<_>>();
-
-        println!("{}", entries.join("\n"));
+                Ok(entry) => Entry::from(entry),
+            };
+            context.prune = false;
+            let matched = config.expr.eval(&entry, &mut context);
+            if matched
+                && config.duplicates.is_some()
+                && entry.file_type().is_file()
+            {
+                found.push(entry.path().to_path_buf());
+            }
+            // the contents were already visited with -depth
+            if context.prune
+                && entry.file_type().is_dir()
+                && !config.options.depth_first
+            {
+                walker.skip_current_dir();
+            }
+        }
     }
 
+    context.finish()?;
+    if let Some(options) = &config.duplicates {
+        let sets = duplicates::find(found, options.hash);
+        if duplicates::report(&sets, options, &mut io::stdout().lock())? {
+            context.failed = true;
+        }
+    }
+    if context.failed {
+        return Err("Some actions failed".into());
+    }
     Ok(())
 }
@@ -0,0 +1,160 @@
+use crate::{action::Context, entry::Entry, Config, Follow, MyResult};
+use ignore::{
+    DirEntry, Error, ParallelVisitor, ParallelVisitorBuilder, WalkBuilder,
+    WalkState,
+};
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+// --------------------------------------------------
+/// What the walker threads send of an entry
+enum Found {
+    /// what the entry printed
+    Printed(PathBuf, Vec<u8>),
+    /// a regular file that matched, with --duplicates
+    File(PathBuf),
+}
+
+// --------------------------------------------------
+/// Walks a path with the walker of the ignore crate, on as many
+/// threads as asked and skipping what ignore files ignore with
+/// --gitignore. Each thread evaluates the expression with its own
+/// context, so what the entries print comes in no particular order
+/// unless sorted. The regular files that match are added to found
+/// with --duplicates. Returns whether an action failed.
+pub fn walk(
+    config: &Config,
+    path: &str,
+    found: &mut Vec<PathBuf>,
+) -> MyResult<bool> {
+    let ignore = config.gitignore;
+    let walker = WalkBuilder::new(path)
+        .standard_filters(false)
+        .git_ignore(ignore)
+        .git_global(ignore)
+        .git_exclude(ignore)
+        .ignore(ignore)
+        .parents(ignore)
+        // as ripgrep does, .gitignore counts outside a git repository too
+        .require_git(false)
+        .threads(config.threads.unwrap_or(1))
+        .min_depth(Some(config.options.min_depth))
+        .max_depth(config.options.max_depth)
+        .same_file_system(config.options.xdev)
+        .follow_links(config.follow == Follow::Always)
+        .build_parallel();
+
+    let failed = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+    let mut builder = Builder {
+        config,
+        tx,
+        failed: &failed,
+    };
+    let printed = thread::scope(|scope| -> io::Result<()> {
+        scope.spawn(move || walker.visit(&mut builder));
+        let mut out = io::stdout().lock();
+        let mut printed = vec![];
+        for message in rx {
+            match message {
+                Found::File(path) => found.push(path),
+                Found::Printed(_, text) if !config.sort => {
+                    out.write_all(&text)?
+                }
+                Found::Printed(path, text) => printed.push((path, text)),
+            }
+        }
+        printed.sort();
+        for (_, text) in printed {
+            out.write_all(&text)?;
+        }
+        out.flush()
+    });
+    printed?;
+    Ok(failed.load(Ordering::Relaxed))
+}
+
+// --------------------------------------------------
+struct Builder<'a> {
+    config: &'a Config,
+    tx: Sender<Found>,
+    failed: &'a AtomicBool,
+}
+
+impl<'a> ParallelVisitorBuilder<'a> for Builder<'a> {
+    fn build(&mut self) -> Box<dyn ParallelVisitor + 'a> {
+        Box::new(Visitor {
+            config: self.config,
+            context: Context::buffered(),
+            tx: self.tx.clone(),
+            failed: self.failed,
+        })
+    }
+}
+
+// --------------------------------------------------
+/// Evaluates the expression on the entries of one thread
+struct Visitor<'a> {
+    config: &'a Config,
+    context: Context,
+    tx: Sender<Found>,
+    failed: &'a AtomicBool,
+}
+
+impl ParallelVisitor for Visitor<'_> {
+    fn visit(&mut self, result: Result<DirEntry, Error>) -> WalkState {
+        let entry = match result {
+            Err(e) => {
+                eprintln!("{}", e);
+                return WalkState::Continue;
+            }
+            Ok(entry) => match Entry::try_from(entry) {
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return WalkState::Continue;
+                }
+                Ok(entry) => entry,
+            },
+        };
+        self.context.prune = false;
+        let matched = self.config.expr.eval(&entry, &mut self.context);
+        let text = self.context.take_output();
+        // the printing only stops early if findr is failing anyway
+        if !text.is_empty() {
+            let path = entry.path().to_path_buf();
+            let _ = self.tx.send(Found::Printed(path, text));
+        }
+        if matched
+            && self.config.duplicates.is_some()
+            && entry.file_type().is_file()
+        {
+            let _ = self.tx.send(Found::File(entry.path().to_path_buf()));
+        }
+        if self.context.prune && entry.file_type().is_dir() {
+            WalkState::Skip
+        } else {
+            WalkState::Continue
+        }
+    }
+}
+
+impl Drop for Visitor<'_> {
+    /// Runs the commands of this thread's -exec ... + once its part of
+    /// the walk is done
+    fn drop(&mut self) {
+        if let Err(e) = self.context.finish() {
+            eprintln!("{}", e);
+            self.context.failed = true;
+        }
+        if self.context.failed {
+            self.failed.store(true, Ordering::Relaxed);
+        }
+    }
+}
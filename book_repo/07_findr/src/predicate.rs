@@ -0,0 +1,807 @@
+use crate::{
+    duplicates::{self, Hash},
+    entry::Entry,
+    EntryType, MyResult,
+};
+use glob::MatchOptions;
+use regex::{Regex, RegexBuilder};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+use users::{get_group_by_name, get_user_by_name};
+
+// --------------------------------------------------
+/// A number given as N, +N or -N, matching values equal to,
+/// greater than or less than N
+#[derive(Debug, PartialEq)]
+pub enum Cmp {
+    Less(i64),
+    Exactly(i64),
+    More(i64),
+}
+
+impl Cmp {
+    pub fn matches(&self, value: i64) -> bool {
+        match self {
+            Cmp::Less(n) => value < *n,
+            Cmp::Exactly(n) => value == *n,
+            Cmp::More(n) => value > *n,
+        }
+    }
+}
+
+// --------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeField {
+    Accessed,
+    Changed,
+    Modified,
+}
+
+// --------------------------------------------------
+/// How -perm compares the permission bits of a file with a mode
+#[derive(Debug, PartialEq)]
+pub enum PermMatch {
+    /// exactly the mode
+    Exact(u32),
+    /// at least all the bits of the mode, as in -mode
+    All(u32),
+    /// any of the bits of the mode, as in /mode
+    Any(u32),
+}
+
+// --------------------------------------------------
+/// A regex that compares equal to another with the same pattern
+#[derive(Debug)]
+pub struct Pattern(pub Regex);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+// --------------------------------------------------
+/// A shell glob, where as in find * and ? also match a slash or a
+/// leading dot
+#[derive(Debug, PartialEq)]
+pub struct Glob {
+    pub pattern: glob::Pattern,
+    pub case_sensitive: bool,
+}
+
+impl Glob {
+    fn matches(&self, value: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: self.case_sensitive,
+            require_literal_separator: false,
+            require_literal_leading_dot: false,
+        };
+        self.pattern.matches_with(value, options)
+    }
+}
+
+// --------------------------------------------------
+/// The syntax of the regexes of -regex, chosen with -regextype
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegexType {
+    /// the syntax of the regex crate
+    Rust,
+    /// POSIX extended regexes, which the regex crate also reads
+    PosixExtended,
+    /// POSIX basic regexes, where \( \) \{ \} \| \+ and \? are
+    /// the operators and ( ) { } | + and ? are plain characters
+    PosixBasic,
+}
+
+// --------------------------------------------------
+/// A test of a file's name, type or metadata
+#[derive(Debug, PartialEq)]
+pub enum Predicate {
+    /// the file name matches a glob
+    Name(Glob),
+    /// the whole path matches a glob
+    Path(Glob),
+    /// the whole path matches a regex
+    Regex(Pattern),
+    /// a line of a regular file that isn't binary matches a regex
+    Contains(Pattern),
+    /// a regular file that isn't in a list of SHA-256 hashes by path,
+    /// or whose contents no longer have the hash listed, the paths
+    /// without any ./ so that ./a and a are the same file
+    NewerHash(HashMap<PathBuf, String>),
+    /// any of the types
+    Type(Vec<EntryType>),
+    /// size rounded up to a number of units of the given bytes
+    Size {
+        cmp: Cmp,
+        unit: u64,
+    },
+    /// whole units of the given seconds between a time and now
+    Age {
+        field: TimeField,
+        cmp: Cmp,
+        unit: i64,
+        now: i64,
+    },
+    /// modified more recently than the time given
+    Newer(SystemTime),
+    Perm(PermMatch),
+    User(u32),
+    Group(u32),
+    /// an empty file or a directory with no entries
+    Empty,
+}
+
+impl Predicate {
+    pub fn is_match(&self, entry: &Entry) -> MyResult<bool> {
+        // names and types don't need the metadata
+        match self {
+            Predicate::Name(glob) => {
+                return Ok(glob.matches(&entry.file_name().to_string_lossy()))
+            }
+            Predicate::Path(glob) => {
+                return Ok(glob.matches(&entry.path().to_string_lossy()))
+            }
+            Predicate::Regex(Pattern(re)) => {
+                return Ok(re.is_match(&entry.path().to_string_lossy()))
+            }
+            Predicate::Contains(Pattern(re)) => {
+                return Ok(
+                    entry.file_type().is_file() && contains(entry.path(), re)?
+                )
+            }
+            Predicate::NewerHash(hashes) => {
+                if !entry.file_type().is_file() {
+                    return Ok(false);
+                }
+                return Ok(match hashes.get(&without_cur_dir(entry.path())) {
+                    None => true,
+                    Some(hash) => {
+                        duplicates::full_hash(entry.path(), Hash::Sha256)?
+                            != *hash
+                    }
+                });
+            }
+            Predicate::Type(entry_types) => {
+                let file_type = entry.file_type();
+                return Ok(entry_types.iter().any(
+                    |entry_type| match entry_type {
+                        EntryType::Link => file_type.is_symlink(),
+                        EntryType::Dir => file_type.is_dir(),
+                        EntryType::File => file_type.is_file(),
+                        EntryType::Socket => file_type.is_socket(),
+                        EntryType::Fifo => file_type.is_fifo(),
+                        EntryType::BlockDevice => file_type.is_block_device(),
+                        EntryType::CharDevice => file_type.is_char_device(),
+                    },
+                ));
+            }
+            _ => {}
+        }
+
+        let metadata = entry.metadata()?;
+        Ok(match self {
+            Predicate::Name(_)
+            | Predicate::Path(_)
+            | Predicate::Regex(_)
+            | Predicate::Contains(_)
+            | Predicate::NewerHash(_)
+            | Predicate::Type(_) => unreachable!(),
+            Predicate::Size { cmp, unit } => {
+                cmp.matches(metadata.len().div_ceil(*unit) as i64)
+            }
+            Predicate::Age {
+                field,
+                cmp,
+                unit,
+                now,
+            } => {
+                let time = match field {
+                    TimeField::Accessed => metadata.atime(),
+                    TimeField::Changed => metadata.ctime(),
+                    TimeField::Modified => metadata.mtime(),
+                };
+                cmp.matches((now - time).div_euclid(*unit))
+            }
+            Predicate::Newer(time) => metadata.modified()? > *time,
+            Predicate::Perm(perm) => {
+                let mode = metadata.mode() & 0o7777;
+                match perm {
+                    PermMatch::Exact(bits) => mode == *bits,
+                    PermMatch::All(bits) => mode & bits == *bits,
+                    PermMatch::Any(bits) => *bits == 0 || mode & bits != 0,
+                }
+            }
+            Predicate::User(uid) => metadata.uid() == *uid,
+            Predicate::Group(gid) => metadata.gid() == *gid,
+            Predicate::Empty => {
+                if metadata.is_dir() {
+                    fs::read_dir(entry.path())?.next().is_none()
+                } else {
+                    metadata.is_file() && metadata.len() == 0
+                }
+            }
+        })
+    }
+}
+
+// --------------------------------------------------
+/// Whether any line of a file, without its newline, matches, reading
+/// it a line at a time as grepr does. A file with a null byte in the
+/// first buffer it reads is taken to be binary, as grep does, and
+/// matches nothing.
+fn contains(path: &Path, re: &Regex) -> io::Result<bool> {
+    let mut file = BufReader::new(File::open(path)?);
+    if file.fill_buf()?.contains(&0) {
+        return Ok(false);
+    }
+    let mut line = vec![];
+    loop {
+        let bytes = file.read_until(b'\n', &mut line)?;
+        if bytes == 0 {
+            return Ok(false);
+        }
+        let text = line.strip_suffix(b"\n").unwrap_or(&line);
+        if re.is_match(&String::from_utf8_lossy(text)) {
+            return Ok(true);
+        }
+        line.clear();
+    }
+}
+
+// --------------------------------------------------
+/// Parses a glob, where a backslash makes the next character plain
+pub fn parse_glob(value: &str, case_sensitive: bool) -> Option<Glob> {
+    let mut escaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c) => {
+                    escaped.push_str(&glob::Pattern::escape(&c.to_string()))
+                }
+                None => escaped.push_str("[\\]"),
+            },
+            _ => escaped.push(c),
+        }
+    }
+    glob::Pattern::new(&escaped).ok().map(|pattern| Glob {
+        pattern,
+        case_sensitive,
+    })
+}
+
+// --------------------------------------------------
+/// Parses a regex of the given syntax, which like find's must
+/// match the whole of a path
+pub fn parse_regex(
+    value: &str,
+    regex_type: RegexType,
+    case_sensitive: bool,
+) -> Option<Pattern> {
+    let value = match regex_type {
+        RegexType::Rust | RegexType::PosixExtended => value.to_string(),
+        RegexType::PosixBasic => from_basic(value),
+    };
+    RegexBuilder::new(&format!("^(?:{})$", value))
+        .case_insensitive(!case_sensitive)
+        .build()
+        .ok()
+        .map(Pattern)
+}
+
+// --------------------------------------------------
+/// Parses the regex of -contains, which like grep's may match
+/// anywhere in a line
+pub fn parse_contains(value: &str, regex_type: RegexType) -> Option<Pattern> {
+    let value = match regex_type {
+        RegexType::Rust | RegexType::PosixExtended => value.to_string(),
+        RegexType::PosixBasic => from_basic(value),
+    };
+    Regex::new(&value).ok().map(Pattern)
+}
+
+// --------------------------------------------------
+/// Rewrites a POSIX basic regex in the syntax of the regex crate
+fn from_basic(value: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = value.chars().peekable();
+    // whether a * here would have nothing to repeat, so is plain
+    let mut at_start = true;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c @ ('(' | ')' | '{' | '}' | '|' | '+' | '?')) => {
+                    regex.push(c);
+                    at_start = matches!(c, '(' | '|');
+                    continue;
+                }
+                Some(c) => {
+                    regex.push('\\');
+                    regex.push(c);
+                }
+                None => regex.push_str("\\\\"),
+            },
+            '(' | ')' | '{' | '}' | '|' | '+' | '?' => {
+                regex.push('\\');
+                regex.push(c);
+            }
+            '*' if at_start => regex.push_str("\\*"),
+            // a bracket expression is copied as it is, and a ] first
+            // in it is a plain character
+            '[' => {
+                regex.push(c);
+                if chars.peek() == Some(&'^') {
+                    regex.push(chars.next().unwrap());
+                }
+                if chars.peek() == Some(&']') {
+                    regex.push_str("\\]");
+                    chars.next();
+                }
+                for c in chars.by_ref() {
+                    regex.push(c);
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            _ => regex.push(c),
+        }
+        at_start = false;
+    }
+    regex
+}
+
+// --------------------------------------------------
+/// Parses the letters of -type, which may be a list as in f,l
+pub fn parse_types(value: &str) -> Option<Predicate> {
+    value
+        .split(',')
+        .map(|letter| match letter {
+            "b" => Some(EntryType::BlockDevice),
+            "c" => Some(EntryType::CharDevice),
+            "d" => Some(EntryType::Dir),
+            "f" => Some(EntryType::File),
+            "l" => Some(EntryType::Link),
+            "p" => Some(EntryType::Fifo),
+            "s" => Some(EntryType::Socket),
+            _ => None,
+        })
+        .collect::<Option<_>>()
+        .map(Predicate::Type)
+}
+
+// --------------------------------------------------
+fn parse_cmp(value: &str) -> Option<Cmp> {
+    let (make, number): (fn(i64) -> Cmp, _) = match value.as_bytes().first() {
+        Some(b'+') => (Cmp::More, &value[1..]),
+        Some(b'-') => (Cmp::Less, &value[1..]),
+        _ => (Cmp::Exactly, value),
+    };
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    number.parse().ok().map(make)
+}
+
+// --------------------------------------------------
+/// Parses [+-]N[bckwMG] as find does, with 512-byte blocks by default
+pub fn parse_size(value: &str) -> Option<Predicate> {
+    let (number, unit) = match value.char_indices().last() {
+        Some((index, c)) if c.is_ascii_alphabetic() => (&value[..index], c),
+        _ => (value, 'b'),
+    };
+    let unit = match unit {
+        'b' => 512,
+        'c' => 1,
+        'w' => 2,
+        'k' => 1024,
+        'M' => 1024 * 1024,
+        'G' => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    let cmp = parse_cmp(number)?;
+    Some(Predicate::Size { cmp, unit })
+}
+
+// --------------------------------------------------
+/// Parses the [+-]N of -mtime and the like, where unit is the
+/// number of seconds in a day or a minute
+pub fn parse_age(
+    value: &str,
+    field: TimeField,
+    unit: i64,
+    now: SystemTime,
+) -> Option<Predicate> {
+    let cmp = parse_cmp(value)?;
+    let now = match now.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    Some(Predicate::Age {
+        field,
+        cmp,
+        unit,
+        now,
+    })
+}
+
+// --------------------------------------------------
+pub fn parse_newer(filename: &str) -> MyResult<Predicate> {
+    let modified = fs::metadata(filename)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| format!("{}: {}", filename, e))?;
+    Ok(Predicate::Newer(modified))
+}
+
+// --------------------------------------------------
+/// Reads a list of hashes as sha256sum prints it, each line a hash,
+/// two spaces, or a space and a star, and a path
+pub fn parse_hashes(filename: &str) -> MyResult<Predicate> {
+    let text = fs::read_to_string(filename)
+        .map_err(|e| format!("{}: {}", filename, e))?;
+    let mut hashes = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let hash = line
+            .get(..64)
+            .filter(|hash| hash.bytes().all(|b| b.is_ascii_hexdigit()));
+        let path = line.get(64..).and_then(|rest| {
+            rest.strip_prefix("  ").or_else(|| rest.strip_prefix(" *"))
+        });
+        match (hash, path) {
+            (Some(hash), Some(path)) if !path.is_empty() => {
+                hashes.insert(
+                    without_cur_dir(Path::new(path)),
+                    hash.to_ascii_lowercase(),
+                );
+            }
+            _ => {
+                return Err(
+                    format!("{}: invalid line {}", filename, i + 1).into()
+                )
+            }
+        }
+    }
+    Ok(Predicate::NewerHash(hashes))
+}
+
+// --------------------------------------------------
+/// The path without . components, as sha256sum * lists a file that
+/// findr reaches as ./a
+fn without_cur_dir(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+// --------------------------------------------------
+/// Parses a mode, prefixed with - or / to match all or any of its
+/// bits, given in octal or symbolically as in "u+w,g=rx"
+pub fn parse_perm(value: &str) -> Option<Predicate> {
+    let (make, mode): (fn(u32) -> PermMatch, _) = match value.as_bytes().first()
+    {
+        Some(b'-') => (PermMatch::All, &value[1..]),
+        Some(b'/') => (PermMatch::Any, &value[1..]),
+        _ => (PermMatch::Exact, value),
+    };
+    let bits = parse_mode(mode)?;
+    Some(Predicate::Perm(make(bits)))
+}
+
+// --------------------------------------------------
+fn parse_mode(mode: &str) -> Option<u32> {
+    if !mode.is_empty() && mode.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+        return u32::from_str_radix(mode, 8)
+            .ok()
+            .filter(|&bits| bits <= 0o7777);
+    }
+
+    // modes start from nothing, so "-" takes nothing away
+    let mut bits = 0;
+    for clause in mode.split(',') {
+        let op_index = clause.find(['+', '-', '='])?;
+        let (who, perms) = clause.split_at(op_index);
+        let mut mask = 0;
+        for c in who.chars() {
+            mask |= match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                'a' => 0o7777,
+                _ => return None,
+            };
+        }
+        if mask == 0 {
+            mask = 0o7777;
+        }
+        let mut wanted = 0;
+        for c in perms[1..].chars() {
+            wanted |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                's' => 0o6000,
+                't' => 0o1000,
+                _ => return None,
+            };
+        }
+        if !perms.starts_with('-') {
+            bits |= wanted & mask;
+        }
+    }
+    Some(bits)
+}
+
+// --------------------------------------------------
+pub fn parse_user(value: &str) -> Option<Predicate> {
+    get_user_by_name(value)
+        .map(|user| user.uid())
+        .or_else(|| value.parse().ok())
+        .map(Predicate::User)
+}
+
+// --------------------------------------------------
+pub fn parse_group(value: &str) -> Option<Predicate> {
+    get_group_by_name(value)
+        .map(|group| group.gid())
+        .or_else(|| value.parse().ok())
+        .map(Predicate::Group)
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{
+        contains, from_basic, parse_age, parse_cmp, parse_contains, parse_glob,
+        parse_hashes, parse_mode, parse_perm, parse_regex, parse_size,
+        parse_types, parse_user, Cmp, PermMatch, Predicate, RegexType,
+        TimeField,
+    };
+    use crate::EntryType;
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    #[test]
+    fn test_parse_glob() {
+        let glob = parse_glob("*.csv", true).unwrap();
+        assert!(glob.matches("a.csv"));
+        assert!(glob.matches(".csv"));
+        assert!(!glob.matches("a.CSV"));
+        assert!(!glob.matches("a.csv.txt"));
+        assert!(parse_glob("*.csv", false).unwrap().matches("a.CSV"));
+
+        let glob = parse_glob("a/*", true).unwrap();
+        assert!(glob.matches("a/b/c.txt"));
+        let glob = parse_glob("[!a]?", true).unwrap();
+        assert!(glob.matches("ba"));
+        assert!(!glob.matches("ab"));
+
+        let glob = parse_glob("\\*\\[", true).unwrap();
+        assert!(glob.matches("*["));
+        assert!(!glob.matches("a["));
+        assert!(parse_glob("[a", true).is_none());
+    }
+
+    #[test]
+    fn test_parse_regex() {
+        let re = parse_regex(".*/b+", RegexType::Rust, true).unwrap();
+        assert!(re.0.is_match("a/bb"));
+        // the whole path must match
+        assert!(!re.0.is_match("a/bb/c"));
+        let re = parse_regex(".*/B", RegexType::PosixExtended, false).unwrap();
+        assert!(re.0.is_match("a/b"));
+
+        let re = parse_regex("a\\(b\\|c\\)+", RegexType::PosixBasic, true);
+        let re = re.unwrap();
+        assert!(re.0.is_match("ab+"));
+        assert!(!re.0.is_match("abb"));
+        assert!(parse_regex("(", RegexType::Rust, true).is_none());
+        assert!(parse_regex("(", RegexType::PosixBasic, true).is_some());
+    }
+
+    #[test]
+    fn test_parse_contains() {
+        // unlike -regex, the regex may match anywhere
+        let re = parse_contains("b+", RegexType::Rust).unwrap();
+        assert!(re.0.is_match("abbc"));
+        let re = parse_contains("a\\|b", RegexType::PosixBasic).unwrap();
+        assert!(re.0.is_match("xb"));
+        assert!(parse_contains("(", RegexType::Rust).is_none());
+    }
+
+    #[test]
+    fn test_contains() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("text");
+        fs::write(&path, b"one\ntwo\n\xff three\n").unwrap();
+        let re = |value| parse_contains(value, RegexType::Rust).unwrap().0;
+        assert!(contains(&path, &re("^two$")).unwrap());
+        // lines that aren't UTF-8 are still searched
+        assert!(contains(&path, &re("three")).unwrap());
+        assert!(!contains(&path, &re("four")).unwrap());
+
+        let path = dir.path().join("binary");
+        fs::write(&path, "one\0two\n").unwrap();
+        assert!(!contains(&path, &re("two")).unwrap());
+        assert!(contains(&dir.path().join("missing"), &re("x")).is_err());
+    }
+
+    #[test]
+    fn test_from_basic() {
+        assert_eq!(from_basic("a\\{2\\}"), "a{2}");
+        assert_eq!(from_basic("a{2}"), "a\\{2\\}");
+        assert_eq!(from_basic("*a*"), "\\*a*");
+        assert_eq!(from_basic("\\(*a\\)"), "(\\*a)");
+        assert_eq!(from_basic("[]|]\\."), "[\\]|]\\.");
+        assert_eq!(from_basic("[^(]"), "[^(]");
+    }
+
+    #[test]
+    fn test_parse_types() {
+        assert_eq!(
+            parse_types("p"),
+            Some(Predicate::Type(vec![EntryType::Fifo]))
+        );
+        assert_eq!(
+            parse_types("f,l,s"),
+            Some(Predicate::Type(vec![
+                EntryType::File,
+                EntryType::Link,
+                EntryType::Socket
+            ]))
+        );
+        for bad in ["", "x", "f,", ",d", "fd", "f,,d"] {
+            assert_eq!(parse_types(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_cmp() {
+        assert_eq!(parse_cmp("3"), Some(Cmp::Exactly(3)));
+        assert_eq!(parse_cmp("+3"), Some(Cmp::More(3)));
+        assert_eq!(parse_cmp("-0"), Some(Cmp::Less(0)));
+        assert_eq!(parse_cmp(""), None);
+        assert_eq!(parse_cmp("+"), None);
+        assert_eq!(parse_cmp("--3"), None);
+        assert_eq!(parse_cmp("3.5"), None);
+
+        assert!(Cmp::Less(3).matches(2));
+        assert!(!Cmp::Less(3).matches(3));
+        assert!(Cmp::More(3).matches(4));
+        assert!(!Cmp::More(3).matches(3));
+        assert!(Cmp::Exactly(3).matches(3));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(
+            parse_size("+10k"),
+            Some(Predicate::Size {
+                cmp: Cmp::More(10),
+                unit: 1024
+            })
+        );
+        assert_eq!(
+            parse_size("-2M"),
+            Some(Predicate::Size {
+                cmp: Cmp::Less(2),
+                unit: 1024 * 1024
+            })
+        );
+        assert_eq!(
+            parse_size("100c"),
+            Some(Predicate::Size {
+                cmp: Cmp::Exactly(100),
+                unit: 1
+            })
+        );
+        assert_eq!(
+            parse_size("4"),
+            Some(Predicate::Size {
+                cmp: Cmp::Exactly(4),
+                unit: 512
+            })
+        );
+        for bad in ["", "k", "+", "1T", "1.5M", "x"] {
+            assert_eq!(parse_size(bad), None);
+        }
+    }
+
+    #[test]
+    fn test_parse_age() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        assert_eq!(
+            parse_age("-7", TimeField::Modified, 86400, now),
+            Some(Predicate::Age {
+                field: TimeField::Modified,
+                cmp: Cmp::Less(7),
+                unit: 86400,
+                now: 1000
+            })
+        );
+        assert_eq!(parse_age("x", TimeField::Changed, 60, now), None);
+    }
+
+    #[test]
+    fn test_parse_perm() {
+        assert_eq!(
+            parse_perm("644"),
+            Some(Predicate::Perm(PermMatch::Exact(0o644)))
+        );
+        assert_eq!(
+            parse_perm("-u+x"),
+            Some(Predicate::Perm(PermMatch::All(0o100)))
+        );
+        assert_eq!(
+            parse_perm("/022"),
+            Some(Predicate::Perm(PermMatch::Any(0o22)))
+        );
+        assert_eq!(parse_perm("8"), None);
+        assert_eq!(parse_perm("17777"), None);
+        assert_eq!(parse_perm("u+q"), None);
+        assert_eq!(parse_perm(""), None);
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("0755"), Some(0o755));
+        assert_eq!(parse_mode("u=rwx,g=rx,o=rx"), Some(0o755));
+        assert_eq!(parse_mode("a+r"), Some(0o444));
+        assert_eq!(parse_mode("+w"), Some(0o222));
+        assert_eq!(parse_mode("ug+s"), Some(0o6000));
+        assert_eq!(parse_mode("o+t"), Some(0o1000));
+        assert_eq!(parse_mode("u-w"), Some(0));
+        assert_eq!(parse_mode("u"), None);
+        assert_eq!(parse_mode("z+r"), None);
+    }
+
+    #[test]
+    fn test_parse_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("SHA256SUMS");
+        let name = path.to_str().unwrap();
+        let hash =
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        fs::write(
+            &path,
+            format!("{}  a b\n\n{} *./c\n", hash, hash.to_uppercase()),
+        )
+        .unwrap();
+        match parse_hashes(name).unwrap() {
+            Predicate::NewerHash(hashes) => {
+                assert_eq!(hashes.len(), 2);
+                assert_eq!(hashes[&PathBuf::from("a b")], hash);
+                // the leading ./ is dropped
+                assert_eq!(hashes[&PathBuf::from("c")], hash);
+            }
+            predicate => panic!("{:?}", predicate),
+        }
+
+        for text in [&hash[1..], "xyz  a", &format!("{} a", hash), hash] {
+            fs::write(&path, format!("{}\n", text)).unwrap();
+            assert_eq!(
+                parse_hashes(name).unwrap_err().to_string(),
+                format!("{}: invalid line 1", name)
+            );
+        }
+        assert!(parse_hashes(&format!("{}.missing", name)).is_err());
+    }
+
+    #[test]
+    fn test_parse_user() {
+        assert_eq!(parse_user("1234"), Some(Predicate::User(1234)));
+        assert_eq!(parse_user("no-such-user-here"), None);
+    }
+}
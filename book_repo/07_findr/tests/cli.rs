@@ -1,7 +1,15 @@
 use assert_cmd::Command;
+use filetime::{set_file_mtime, FileTime};
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::{borrow::Cow, fs, path::Path};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -39,19 +47,19 @@ fn skips_bad_dir() -> TestResult {
 #[test]
 fn dies_bad_name() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["--name", "*.csv"])
+        .args(["--name", "[a"])
         .assert()
         .failure()
-        .stderr(predicate::str::contains("Invalid --name \"*.csv\""));
+        .stderr(predicate::str::contains("Invalid --name \"[a\""));
     Ok(())
 }
 
 // --------------------------------------------------
 #[test]
 fn dies_bad_type() -> TestResult {
-    let expected = "error: 'x' isn't a valid value for '--type <TYPE>...'";
+    let expected = "Invalid --type \"x\"";
     Command::cargo_bin(PRG)?
-        .args(&["--type", "x"])
+        .args(["--type", "x"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -59,23 +67,89 @@ fn dies_bad_type() -> TestResult {
 }
 
 // --------------------------------------------------
-#[cfg(windows)]
-fn format_file_name(expected_file: &str) -> Cow<str> {
-    // Equivalent to: Cow::Owned(format!("{}.windows", expected_file))
-    format!("{}.windows", expected_file).into()
+#[test]
+fn dies_bad_size() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--size", "10T"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --size \"10T\""));
+    Ok(())
 }
 
 // --------------------------------------------------
-#[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
-    // Equivalent to: Cow::Borrowed(expected_file)
-    expected_file.into()
+#[test]
+fn dies_bad_mtime() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--mtime", "+x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --mtime \"+x\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_perm() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--perm", "u+z"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --perm \"u+z\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_newer() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = format!("{}: .* [(]os error 2[)]", &bad);
+    Command::cargo_bin(PRG)?
+        .args(["--newer", &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_predicate() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-foo"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown predicate \"-foo\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_missing_paren() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "(", "-t", "f"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Missing \")\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_path_after_expression() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-t", "f", "tests/inputs"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Paths must precede expression: \"tests/inputs\"",
+        ));
+    Ok(())
 }
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
-    let file = format_file_name(expected_file);
-    let contents = fs::read_to_string(file.as_ref())?;
+    let contents = fs::read_to_string(expected_file)?;
     let mut expected: Vec<&str> =
         contents.split("\n").filter(|s| !s.is_empty()).collect();
     expected.sort();
@@ -215,11 +289,20 @@ fn type_l() -> TestResult {
     run(&["tests/inputs", "-t", "l"], "tests/expected/type_l.txt")
 }
 
+// --------------------------------------------------
+#[test]
+fn type_d_l() -> TestResult {
+    run(
+        &["tests/inputs", "-type", "d,l"],
+        "tests/expected/type_d_l.txt",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn type_f_l() -> TestResult {
     run(
-        &["tests/inputs", "-t", "l", "f"],
+        &["tests/inputs", "-t", "l", "-o", "-t", "f"],
         "tests/expected/type_f_l.txt",
     )
 }
@@ -228,7 +311,7 @@ fn type_f_l() -> TestResult {
 #[test]
 fn name_csv() -> TestResult {
     run(
-        &["tests/inputs", "-n", ".*[.]csv"],
+        &["tests/inputs", "-n", "*.csv"],
         "tests/expected/name_csv.txt",
     )
 }
@@ -237,7 +320,7 @@ fn name_csv() -> TestResult {
 #[test]
 fn name_csv_mp3() -> TestResult {
     run(
-        &["tests/inputs", "-n", ".*[.]csv", "-n", ".*[.]mp3"],
+        &["tests/inputs", "-n", "*.csv", "-o", "-n", "*.mp3"],
         "tests/expected/name_csv_mp3.txt",
     )
 }
@@ -246,7 +329,7 @@ fn name_csv_mp3() -> TestResult {
 #[test]
 fn name_txt_path_a_d() -> TestResult {
     run(
-        &["tests/inputs/a", "tests/inputs/d", "--name", ".*.txt"],
+        &["tests/inputs/a", "tests/inputs/d", "--name", "*.txt"],
         "tests/expected/name_txt_path_a_d.txt",
     )
 }
@@ -254,14 +337,14 @@ fn name_txt_path_a_d() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn name_a() -> TestResult {
-    run(&["tests/inputs", "-n", "a"], "tests/expected/name_a.txt")
+    run(&["tests/inputs", "-n", "a*"], "tests/expected/name_a.txt")
 }
 
 // --------------------------------------------------
 #[test]
 fn type_f_name_a() -> TestResult {
     run(
-        &["tests/inputs", "-t", "f", "-n", "a"],
+        &["tests/inputs", "-t", "f", "-n", "a*"],
         "tests/expected/type_f_name_a.txt",
     )
 }
@@ -270,11 +353,53 @@ fn type_f_name_a() -> TestResult {
 #[test]
 fn type_d_name_a() -> TestResult {
     run(
-        &["tests/inputs", "--type", "d", "--name", "a"],
+        &["tests/inputs", "--type", "d", "--name", "a*"],
         "tests/expected/type_d_name_a.txt",
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn iname_csv() -> TestResult {
+    run(
+        &["tests/inputs", "-iname", "*.C[sS]V"],
+        "tests/expected/iname_csv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn path_glob_b() -> TestResult {
+    run(
+        &["tests/inputs", "-path", "*/b/*"],
+        "tests/expected/path_glob_b.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_txt() -> TestResult {
+    run(
+        &["tests/inputs", "-regex", r".*/[a-d]\.txt"],
+        "tests/expected/regex_txt.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_basic() -> TestResult {
+    run(
+        &[
+            "tests/inputs",
+            "-regextype",
+            "posix-basic",
+            "-iregex",
+            r".*/\(A\|D\)\.T.*",
+        ],
+        "tests/expected/regex_basic.txt",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn path_g() -> TestResult {
@@ -283,7 +408,836 @@ fn path_g() -> TestResult {
 
 // --------------------------------------------------
 #[test]
-#[cfg(not(windows))]
+fn not_type_d() -> TestResult {
+    run(
+        &["tests/inputs", "!", "-type", "d"],
+        "tests/expected/not_type_d.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn type_f_csv_or_mp3() -> TestResult {
+    run(
+        &[
+            "tests/inputs",
+            "-type",
+            "f",
+            "(",
+            "-name",
+            "*csv*",
+            "-o",
+            "-name",
+            "*mp3*",
+            ")",
+        ],
+        "tests/expected/type_f_csv_or_mp3.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn type_f_not_txt_csv() -> TestResult {
+    run(
+        &[
+            "tests/inputs",
+            "-type",
+            "f",
+            "-not",
+            "(",
+            "-name",
+            "*txt*",
+            "-or",
+            "-name",
+            "*csv*",
+            ")",
+        ],
+        "tests/expected/type_f_not_txt_csv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn name_a_dir_or_tsv() -> TestResult {
+    // -and binds tighter than -or
+    run(
+        &[
+            "tests/inputs",
+            "-name",
+            "*a*",
+            "-and",
+            "-type",
+            "d",
+            "-o",
+            "-name",
+            "*.tsv",
+        ],
+        "tests/expected/name_a_dir_or_tsv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn type_f_size_2c() -> TestResult {
+    run(
+        &["tests/inputs", "-t", "f", "--size", "2c"],
+        "tests/expected/type_f_size_2c.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn size_less_1k() -> TestResult {
+    // sizes are rounded up, so only empty files are under 1k
+    run(
+        &["tests/inputs", "--size", "-1k"],
+        "tests/expected/size_less_1k.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn maxdepth_1() -> TestResult {
+    run(
+        &["tests/inputs", "-maxdepth", "1"],
+        "tests/expected/maxdepth_1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn type_f_depth_2_3() -> TestResult {
+    run(
+        &[
+            "tests/inputs",
+            "-mindepth",
+            "2",
+            "-maxdepth",
+            "3",
+            "-t",
+            "f",
+        ],
+        "tests/expected/type_f_depth_2_3.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prune_a() -> TestResult {
+    run(
+        &["tests/inputs", "-name", "a", "-prune", "-o", "-type", "f"],
+        "tests/expected/prune_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_type_f() -> TestResult {
+    run(
+        &["-L", "tests/inputs", "-type", "f"],
+        "tests/expected/follow_type_f.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_maxdepth() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-maxdepth", "x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid -maxdepth \"x\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_root_link() -> TestResult {
+    let dir = make_tree()?;
+    let link = tempfile::tempdir()?;
+    let root = link.path().join("root");
+    std::os::unix::fs::symlink(dir.path(), &root)?;
+    let root = root.to_str().unwrap();
+
+    let find = |args: &[&str]| -> Result<usize, Box<dyn std::error::Error>> {
+        let cmd = Command::cargo_bin(PRG)?.args(args).assert().success();
+        let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+        Ok(stdout.lines().filter(|line| !line.is_empty()).count())
+    };
+    // the link itself, unless it is followed
+    assert_eq!(find(&[root, "-t", "f"])?, 0);
+    assert_eq!(find(&["-P", root, "-t", "l"])?, 1);
+    assert_eq!(find(&["-H", root, "-t", "f"])?, 3);
+    assert_eq!(find(&["-L", "-P", root, "-t", "f"])?, 0);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_matches() -> TestResult {
+    // nothing at all is printed, not even a blank line
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-name", "nothing"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn special_types() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let _socket =
+        std::os::unix::net::UnixListener::bind(dir.path().join("socket"))?;
+    std::process::Command::new("mkfifo")
+        .arg(dir.path().join("fifo"))
+        .status()?;
+    fs::write(dir.path().join("file"), "")?;
+
+    let find = |args: &[&str]| -> Result<String, Box<dyn std::error::Error>> {
+        let cmd = Command::cargo_bin(PRG)?
+            .arg("--sort")
+            .arg(dir.path())
+            .args(args)
+            .args(["-printf", "%f\\n"])
+            .assert()
+            .success();
+        Ok(String::from_utf8(cmd.get_output().stdout.clone())?)
+    };
+    assert_eq!(find(&["-type", "s"])?, "socket\n");
+    assert_eq!(find(&["-type", "p"])?, "fifo\n");
+    assert_eq!(find(&["-type", "p,s,f"])?, "fifo\nfile\nsocket\n");
+    assert_eq!(find(&["-type", "b,c"])?, "");
+
+    Command::cargo_bin(PRG)?
+        .args(["/dev/null", "-type", "c"])
+        .assert()
+        .success()
+        .stdout("/dev/null\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+/// Makes a directory of files with known ages and permissions:
+/// old.txt is 10 days old, new.txt and empty.txt are new, and the
+/// directory empty is empty
+fn make_tree() -> Result<TempDir, Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let old = dir.path().join("old.txt");
+    fs::write(&old, "old\n")?;
+    let ten_days = Duration::from_secs(10 * 24 * 60 * 60);
+    set_file_mtime(
+        &old,
+        FileTime::from_system_time(SystemTime::now() - ten_days),
+    )?;
+    fs::set_permissions(&old, fs::Permissions::from_mode(0o600))?;
+
+    let new = dir.path().join("new.txt");
+    fs::write(&new, "new\n")?;
+    fs::set_permissions(&new, fs::Permissions::from_mode(0o755))?;
+
+    fs::write(dir.path().join("empty.txt"), "")?;
+    fs::set_permissions(
+        dir.path().join("empty.txt"),
+        fs::Permissions::from_mode(0o644),
+    )?;
+    fs::create_dir(dir.path().join("empty"))?;
+    Ok(dir)
+}
+
+// --------------------------------------------------
+/// Runs findr on the directory, returning the file names found
+/// relative to it
+fn run_tree(
+    dir: &TempDir,
+    args: &[&str],
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let root = dir.path().to_str().unwrap();
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(root)
+        .args(args)
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut names: Vec<String> = stdout
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            // lines that aren't paths, as from -printf, are kept whole
+            match line.strip_prefix(root) {
+                Some(name) => name.trim_start_matches('/').to_string(),
+                None => line.to_string(),
+            }
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+// --------------------------------------------------
+#[test]
+fn mtime() -> TestResult {
+    let dir = make_tree()?;
+    assert_eq!(run_tree(&dir, &["-t", "f", "--mtime", "+7"])?, ["old.txt"]);
+    assert_eq!(
+        run_tree(&dir, &["-t", "f", "--mtime", "-1"])?,
+        ["empty.txt", "new.txt"]
+    );
+    assert_eq!(run_tree(&dir, &["-t", "f", "--mtime", "10"])?, ["old.txt"]);
+    assert_eq!(
+        run_tree(&dir, &["-t", "f", "--mmin", "-5"])?,
+        ["empty.txt", "new.txt"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn newer() -> TestResult {
+    let dir = make_tree()?;
+    let old = dir.path().join("old.txt");
+    assert_eq!(
+        run_tree(&dir, &["-t", "f", "--newer", old.to_str().unwrap()])?,
+        ["empty.txt", "new.txt"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn perm() -> TestResult {
+    let dir = make_tree()?;
+    assert_eq!(run_tree(&dir, &["-t", "f", "--perm", "600"])?, ["old.txt"]);
+    assert_eq!(
+        run_tree(&dir, &["-t", "f", "--perm", "u=rwx,go=rx"])?,
+        ["new.txt"]
+    );
+    assert_eq!(
+        run_tree(&dir, &["-t", "f", "--perm", "-g+r"])?,
+        ["empty.txt", "new.txt"]
+    );
+    assert_eq!(run_tree(&dir, &["-t", "f", "--perm", "/011"])?, ["new.txt"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn size_empty() -> TestResult {
+    let dir = make_tree()?;
+    assert_eq!(run_tree(&dir, &["--size", "4c"])?, ["new.txt", "old.txt"]);
+    assert_eq!(run_tree(&dir, &["--empty"])?, ["empty", "empty.txt"]);
+    assert_eq!(run_tree(&dir, &["--empty", "-t", "d"])?, ["empty"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn user_group() -> TestResult {
+    let dir = make_tree()?;
+    let metadata = fs::metadata(dir.path())?;
+    let uid = std::os::unix::fs::MetadataExt::uid(&metadata).to_string();
+    let gid = std::os::unix::fs::MetadataExt::gid(&metadata).to_string();
+    assert_eq!(run_tree(&dir, &["-t", "f", "--user", &uid])?.len(), 3);
+    assert_eq!(run_tree(&dir, &["-t", "f", "--group", &gid])?.len(), 3);
+    assert!(run_tree(&dir, &["--user", "4294967"])?.is_empty());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn print0() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/d", "-name", "d*", "-t", "f", "-print0"])
+        .assert()
+        .success()
+        .stdout("tests/inputs/d/d.tsv\0tests/inputs/d/d.txt\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn printf() -> TestResult {
+    let dir = make_tree()?;
+    assert_eq!(
+        run_tree(&dir, &["-t", "f", "-printf", "%f %s %m\\n"])?,
+        ["empty.txt 0 644", "new.txt 4 755", "old.txt 4 600"]
+    );
+    // an action replaces the implicit -print
+    assert_eq!(
+        run_tree(&dir, &["-name", "old*", "-printf", "%p|%%\\n"])?,
+        ["old.txt|%"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn exec() -> TestResult {
+    let dir = make_tree()?;
+    assert_eq!(
+        run_tree(&dir, &["-t", "f", "-exec", "echo", "{}", ";"])?,
+        ["empty.txt", "new.txt", "old.txt"]
+    );
+    // the exit status of the command is the value of -exec
+    assert_eq!(
+        run_tree(
+            &dir,
+            &["-t", "f", "-exec", "test", "-s", "{}", ";", "-print"]
+        )?,
+        ["new.txt", "old.txt"]
+    );
+    assert_eq!(
+        run_tree(
+            &dir,
+            &["-t", "f", "-name", "*.txt", "-execdir", "echo", "={}=", ";"]
+        )?,
+        ["=./empty.txt=", "=./new.txt=", "=./old.txt="]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn exec_batch() -> TestResult {
+    let dir = make_tree()?;
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-t", "f", "-execdir", "echo", "files", "{}", "+"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut words: Vec<&str> = stdout.split_whitespace().collect();
+    words.sort();
+    assert_eq!(stdout.lines().count(), 1);
+    assert_eq!(words, ["./empty.txt", "./new.txt", "./old.txt", "files"]);
+
+    // a command that fails makes findr fail too
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-exec", "false", "{}", "+"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn ok() -> TestResult {
+    let dir = make_tree()?;
+    let root = dir.path().to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args([root, "-name", "old*", "-ok", "echo", "yes", ";"])
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stdout("yes\n")
+        .stderr("< echo yes > ? ");
+    Command::cargo_bin(PRG)?
+        .args([root, "-name", "old*", "-ok", "echo", "yes", ";"])
+        .write_stdin("n\n")
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn delete() -> TestResult {
+    let dir = make_tree()?;
+    fs::create_dir(dir.path().join("empty/sub"))?;
+    fs::write(dir.path().join("empty/sub/gone.txt"), "")?;
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-regex", ".*/empty(/.*)?", "-delete"])
+        .assert()
+        .success()
+        .stdout("");
+    // the contents were deleted before the directories
+    assert_eq!(
+        run_tree(&dir, &["-mindepth", "1"])?,
+        ["empty.txt", "new.txt", "old.txt"]
+    );
+
+    // a directory that isn't empty can't be deleted
+    fs::create_dir(dir.path().join("full"))?;
+    fs::write(dir.path().join("full/kept.txt"), "")?;
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-name", "full", "-delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("full: Directory not empty"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_threads() -> TestResult {
+    let find = |args: &[&str]| -> Result<String, Box<dyn std::error::Error>> {
+        let cmd = Command::cargo_bin(PRG)?.args(args).assert().success();
+        Ok(String::from_utf8(cmd.get_output().stdout.clone())?)
+    };
+    // the same order however the tree is walked
+    let sorted = find(&["--sort", "tests/inputs"])?;
+    assert_eq!(find(&["-j", "4", "--sort", "tests/inputs"])?, sorted);
+    assert_eq!(find(&["--gitignore", "--sort", "tests/inputs"])?, sorted);
+
+    let mut lines: Vec<&str> = sorted.lines().collect();
+    assert_eq!(
+        lines[..3],
+        ["tests/inputs", "tests/inputs/a", "tests/inputs/a/a.txt"]
+    );
+    lines.sort();
+    let mut expected: Vec<String> =
+        fs::read_to_string("tests/expected/path1.txt")?
+            .lines()
+            .map(String::from)
+            .collect();
+    expected.sort();
+    assert_eq!(lines, expected);
+
+    let threaded =
+        find(&["-j", "4", "tests/inputs", "-t", "f", "-printf", "%f\\n"])?;
+    let mut names: Vec<&str> = threaded.lines().collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "a.txt", "b.csv", "c.mp3", "d.tsv", "d.txt", "e.mp3", "f.txt",
+            "g.csv"
+        ]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn gitignore() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path().join("repo");
+    for sub in [".git", "src", "target", "node_modules"] {
+        fs::create_dir_all(root.join(sub))?;
+    }
+    fs::write(root.join(".gitignore"), "target/\n*.log\n")?;
+    fs::write(root.join(".ignore"), "node_modules\n")?;
+    for file in [
+        "src/main.rs",
+        "target/main",
+        "node_modules/m.js",
+        "a.log",
+        "b.tmp",
+    ] {
+        fs::write(root.join(file), "")?;
+    }
+    // the global excludes come from the git config in the home directory
+    let config = dir.path().join("config/git");
+    fs::create_dir_all(&config)?;
+    fs::write(config.join("ignore"), "*.tmp\n")?;
+
+    let find =
+        |args: &[&str]| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let cmd = Command::cargo_bin(PRG)?
+                .env("HOME", dir.path())
+                .env("XDG_CONFIG_HOME", dir.path().join("config"))
+                .args(args)
+                .arg(&root)
+                .args(["-t", "f", "-printf", "%f\\n"])
+                .assert()
+                .success();
+            let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+            Ok(stdout.lines().map(String::from).collect())
+        };
+    assert_eq!(
+        find(&["--gitignore", "--sort"])?,
+        [".gitignore", ".ignore", "main.rs"]
+    );
+    assert_eq!(find(&["--sort", "-j", "2"])?.len(), 7);
+    assert_eq!(find(&["--sort"])?.len(), 7);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn gitignore_without_git() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("target"))?;
+    fs::write(dir.path().join(".gitignore"), "target/\n")?;
+    fs::write(dir.path().join("main.rs"), "")?;
+    fs::write(dir.path().join("target/main"), "")?;
+    Command::cargo_bin(PRG)?
+        .args(["--gitignore", "--sort"])
+        .arg(dir.path())
+        .args(["-t", "f", "-printf", "%f\\n"])
+        .assert()
+        .success()
+        .stdout(".gitignore\nmain.rs\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_threads() -> TestResult {
+    for (args, expected) in [
+        (vec!["-j", "0"], "Invalid --threads \"0\""),
+        (
+            vec!["--gitignore", ".", "-delete"],
+            "-depth or -delete can't be used with --gitignore or --threads",
+        ),
+        (vec!["-H", "-j", "2", "."], "-H can't be used with"),
+        (
+            vec!["-j", "2", ".", "-ok", "ls", ";"],
+            "-ok can't be used with more than one thread",
+        ),
+        (
+            vec![".", "--gitignore"],
+            "--gitignore must come before the paths",
+        ),
+        (
+            vec![".", "-name", "a", "-j", "2"],
+            "-j must come before the paths",
+        ),
+        (
+            vec![".", "--duplicates"],
+            "--duplicates must come before the paths",
+        ),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(&args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn contains() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("foo"))?;
+    fs::write(dir.path().join("a.txt"), "The quick brown fox\n")?;
+    fs::write(dir.path().join("b.txt"), "jumps over\nthe lazy dog\n")?;
+    fs::write(dir.path().join("c.bin"), b"fox\0dog\n")?;
+    fs::write(dir.path().join("foo/d.txt"), "no match\n")?;
+
+    assert_eq!(run_tree(&dir, &["-contains", "fox"])?, ["a.txt"]);
+    assert_eq!(
+        run_tree(&dir, &["-contains", "(?i)^the"])?,
+        ["a.txt", "b.txt"]
+    );
+    // the contents of a directory are not searched as its name
+    assert_eq!(run_tree(&dir, &["-contains", "foo"])?, Vec::<String>::new());
+    assert_eq!(
+        run_tree(&dir, &["-name", "*.txt", "!", "-contains", "dog"])?,
+        ["a.txt", "foo/d.txt"]
+    );
+    assert_eq!(
+        run_tree(
+            &dir,
+            &["-regextype", "posix-basic", "-contains", "\\(fox\\|dog\\)$"]
+        )?,
+        ["a.txt", "b.txt"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn newer_than_file_hash() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = |name: &str| dir.path().join(name);
+    fs::create_dir(path("s"))?;
+    for name in ["a", "b", "s/c"] {
+        fs::write(path(name), name)?;
+    }
+    // a list of hashes as sha256sum prints it, with the paths of findr
+    let manifest = tempfile::NamedTempFile::new()?;
+    let lines: String = ["a", "b", "s/c"]
+        .iter()
+        .map(|name| {
+            let hash = Sha256::digest(name.as_bytes());
+            format!("{:x}  {}\n", hash, path(name).display())
+        })
+        .collect();
+    fs::write(manifest.path(), lines)?;
+    let args = ["-newer-than-file-hash", manifest.path().to_str().unwrap()];
+    assert_eq!(run_tree(&dir, &args)?, Vec::<String>::new());
+
+    fs::write(path("b"), "changed")?;
+    fs::write(path("s/d"), "new")?;
+    // touching a file doesn't change its hash
+    fs::write(path("a"), "a")?;
+    assert_eq!(run_tree(&dir, &args)?, ["b", "s/d"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn newer_than_file_hash_relative() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    for name in ["a", "b", "c"] {
+        fs::write(dir.path().join(name), name)?;
+    }
+    // sha256sum lists a, where findr finds ./a
+    let output = std::process::Command::new("sh")
+        .args(["-c", "sha256sum *"])
+        .current_dir(dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let manifest = tempfile::NamedTempFile::new()?;
+    fs::write(manifest.path(), output.stdout)?;
+    fs::write(dir.path().join("b"), "changed")?;
+
+    let manifest = manifest.path().to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args(["-newer-than-file-hash", manifest])
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout("./b\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+/// Makes a directory where a, b and s/d hold the same text, as do
+/// c and s/e, and f is unlike any other
+fn make_duplicates() -> Result<TempDir, Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("s"))?;
+    for (name, text) in [
+        ("a", "hello\n"),
+        ("b", "hello\n"),
+        ("s/d", "hello\n"),
+        ("c", "hi\n"),
+        ("s/e", "hi\n"),
+        ("f", "hey\n"),
+    ] {
+        fs::write(dir.path().join(name), text)?;
+    }
+    Ok(dir)
+}
+
+// --------------------------------------------------
+/// Runs findr --duplicates in the directory, returning what it prints
+fn run_duplicates(
+    dir: &TempDir,
+    args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let cmd = Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .arg("--duplicates")
+        .args(args)
+        .assert()
+        .success();
+    Ok(String::from_utf8(cmd.get_output().stdout.clone())?)
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates() -> TestResult {
+    let dir = make_duplicates()?;
+    let expected = "./a\n./b\n./s/d\n\n./c\n./s/e\n";
+    assert_eq!(run_duplicates(&dir, &["."])?, expected);
+    assert_eq!(run_duplicates(&dir, &["-j", "2", "."])?, expected);
+    // the expression picks the files compared
+    assert_eq!(run_duplicates(&dir, &[".", "-name", "[ab]"])?, "./a\n./b\n");
+    assert_eq!(
+        run_duplicates(&dir, &[".", "-maxdepth", "1"])?,
+        "./a\n./b\n"
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates_json() -> TestResult {
+    let dir = make_duplicates()?;
+    let sha256 =
+        "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+    assert_eq!(
+        run_duplicates(
+            &dir,
+            &["--json", "--hash", "sha256", ".", "-name", "[ab]"]
+        )?,
+        format!(
+            "[{{\"files\":[\"./a\",\"./b\"],\"hash\":\"{}\",\"size\":6}}]\n",
+            sha256
+        )
+    );
+    let blake3 = blake3::hash(b"hello\n").to_hex().to_string();
+    assert!(run_duplicates(&dir, &["--json", "."])?.contains(&blake3));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates_delete() -> TestResult {
+    let dir = make_duplicates()?;
+    let expected = "delete ./b\ndelete ./s/d\ndelete ./s/e\n";
+    assert_eq!(
+        run_duplicates(&dir, &["--delete-dups", "--dry-run", "."])?,
+        expected
+    );
+    assert_eq!(run_tree(&dir, &["-type", "f"])?.len(), 6);
+
+    assert_eq!(run_duplicates(&dir, &["--delete-dups", "."])?, expected);
+    assert_eq!(run_tree(&dir, &["-type", "f"])?, ["a", "c", "f"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates_hardlink() -> TestResult {
+    let dir = make_duplicates()?;
+    assert_eq!(
+        run_duplicates(&dir, &["--hardlink", "."])?,
+        "link ./b => ./a\nlink ./s/d => ./a\nlink ./s/e => ./c\n"
+    );
+    let inode = |name: &str| -> std::io::Result<u64> {
+        Ok(std::os::unix::fs::MetadataExt::ino(&fs::metadata(
+            dir.path().join(name),
+        )?))
+    };
+    assert_eq!(inode("b")?, inode("a")?);
+    assert_eq!(inode("s/d")?, inode("a")?);
+    assert_eq!(inode("s/e")?, inode("c")?);
+    assert_eq!(fs::read_to_string(dir.path().join("s/e"))?, "hi\n");
+    // names of the same file are not duplicates
+    assert_eq!(run_duplicates(&dir, &["."])?, "");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_duplicates() -> TestResult {
+    for (args, expected) in [
+        (
+            vec!["--duplicates", "--dry-run", "."],
+            "--dry-run needs --hardlink or --delete-dups",
+        ),
+        (
+            vec!["--duplicates", ".", "-print"],
+            "Actions can't be used with --duplicates",
+        ),
+        (vec!["--json", "."], "--duplicates"),
+        (
+            vec!["--duplicates", "--hardlink", "--delete-dups"],
+            "cannot be used with",
+        ),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(&args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
 fn unreadable_dir() -> TestResult {
     let dirname = "tests/inputs/cant-touch-this";
     if !Path::new(dirname).exists() {
@@ -295,7 +1249,7 @@ fn unreadable_dir() -> TestResult {
     //permissions.set_mode(0o000);
 
     std::process::Command::new("chmod")
-        .args(&["000", dirname])
+        .args(["000", dirname])
         .status()
         .expect("failed");
 
@@ -26,3 +26,15 @@ for POS in 1 2 8 1-2 2-3 1-8; do
 done
 
 echo -e "AA\nÉÉ\nSS\nJJ" > "$OUT_DIR/books.c1,1.out"
+
+MIXED="tests/inputs/mixed.tsv"
+cut -f 2 --complement $TSV > "$OUT_DIR/$(basename $TSV).f2.complement.out"
+cut -c 1-2,4-5 --complement $TSV > "$OUT_DIR/$(basename $TSV).c1-2,4-5.complement.out"
+cut -f 1,3 -d , --output-delimiter " | " $CSV > "$OUT_DIR/$(basename $CSV).f1,3.output-delim.out"
+cut -c 1-2,4-5 --output-delimiter : $TSV > "$OUT_DIR/$(basename $TSV).c1-2,4-5.output-delim.out"
+cut -f 2 $MIXED > "$OUT_DIR/$(basename $MIXED).f2.out"
+cut -f 2 -s $MIXED > "$OUT_DIR/$(basename $MIXED).f2.s.out"
+cut -f 2- $TSV > "$OUT_DIR/$(basename $TSV).f2-.out"
+cut -f -2 -d , $CSV > "$OUT_DIR/$(basename $CSV).f-2.dcomma.out"
+cut -b 4-,-2 --output-delimiter : $TSV > "$OUT_DIR/$(basename $TSV).b4-,-2.output-delim.out"
+cut -f 3,1,1-1 $TSV > "$OUT_DIR/$(basename $TSV).f3,1,1-1.input-order.out"
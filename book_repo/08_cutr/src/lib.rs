@@ -3,11 +3,13 @@ use clap::{App, Arg};
 use csv::{ReaderBuilder, StringRecord, WriterBuilder};
 use regex::Regex;
 use std::{
+    borrow::Cow,
     error::Error,
     fs::File,
     io::{self, BufRead, BufReader},
     num::NonZeroUsize,
     ops::Range,
+    slice,
 };
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
@@ -25,6 +27,9 @@ pub struct Config {
     files: Vec<String>,
     delimiter: u8,
     extract: Extract,
+    complement: bool,
+    output_delimiter: Option<String>,
+    only_delimited: bool,
 }
 
 // --------------------------------------------------
@@ -54,6 +59,7 @@ pub fn get_args() -> MyResult<Config> {
                 .short("f")
                 .long("fields")
                 .help("Selected fields")
+                .allow_hyphen_values(true)
                 .conflicts_with_all(&["chars", "bytes"]),
         )
         .arg(
@@ -62,6 +68,7 @@ pub fn get_args() -> MyResult<Config> {
                 .short("b")
                 .long("bytes")
                 .help("Selected bytes")
+                .allow_hyphen_values(true)
                 .conflicts_with_all(&["fields", "chars"]),
         )
         .arg(
@@ -70,8 +77,32 @@ pub fn get_args() -> MyResult<Config> {
                 .short("c")
                 .long("chars")
                 .help("Selected characters")
+                .allow_hyphen_values(true)
                 .conflicts_with_all(&["fields", "bytes"]),
         )
+        .arg(
+            Arg::with_name("complement")
+                .long("complement")
+                .help("Select all but the selected positions"),
+        )
+        .arg(
+            Arg::with_name("output_delimiter")
+                .value_name("STRING")
+                .long("output-delimiter")
+                .help("Output delimiter [default: the field delimiter]"),
+        )
+        .arg(
+            Arg::with_name("only_delimited")
+                .short("s")
+                .long("only-delimited")
+                .help("Skip lines without the field delimiter")
+                .requires("fields"),
+        )
+        .arg(
+            Arg::with_name("input_order")
+                .long("input-order")
+                .help("Select in input order and only once, as GNU cut"),
+        )
         .get_matches();
 
     let delimiter = matches.value_of("delimiter").unwrap();
@@ -83,9 +114,16 @@ pub fn get_args() -> MyResult<Config> {
         )));
     }
 
-    let fields = matches.value_of("fields").map(parse_pos).transpose()?;
-    let bytes = matches.value_of("bytes").map(parse_pos).transpose()?;
-    let chars = matches.value_of("chars").map(parse_pos).transpose()?;
+    let input_order = matches.is_present("input_order");
+    let parse = |value| {
+        parse_pos(value).map(|pos| match input_order {
+            true => in_input_order(pos),
+            false => pos,
+        })
+    };
+    let fields = matches.value_of("fields").map(parse).transpose()?;
+    let bytes = matches.value_of("bytes").map(parse).transpose()?;
+    let chars = matches.value_of("chars").map(parse).transpose()?;
 
     let extract = if let Some(field_pos) = fields {
         Fields(field_pos)
@@ -101,11 +139,15 @@ pub fn get_args() -> MyResult<Config> {
         files: matches.values_of_lossy("files").unwrap(),
         delimiter: *delim_bytes.first().unwrap(),
         extract,
+        complement: matches.is_present("complement"),
+        output_delimiter: matches.value_of("output_delimiter").map(From::from),
+        only_delimited: matches.is_present("only_delimited"),
     })
 }
 
 // --------------------------------------------------
 pub fn run(config: Config) -> MyResult<()> {
+    let output_delimiter = config.output_delimiter.as_deref();
     for filename in &config.files {
         match open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
@@ -114,25 +156,69 @@ pub fn run(config: Config) -> MyResult<()> {
                     let mut reader = ReaderBuilder::new()
                         .delimiter(config.delimiter)
                         .has_headers(false)
+                        .flexible(true)
                         .from_reader(file);
 
+                    // The csv writer quotes what it must for a delimiter
+                    // of one byte, and join_fields for a longer one
+                    let out_delimiter = match output_delimiter {
+                        Some(delim) if delim.len() == 1 => delim.as_bytes()[0],
+                        _ => config.delimiter,
+                    };
                     let mut wtr = WriterBuilder::new()
-                        .delimiter(config.delimiter)
+                        .delimiter(out_delimiter)
+                        .flexible(true)
                         .from_writer(io::stdout());
 
                     for record in reader.records() {
                         let record = record?;
-                        wtr.write_record(extract_fields(&record, field_pos))?;
+                        // A line without the delimiter is a single field,
+                        // which is printed whole unless skipped with -s
+                        let fields = if record.len() == 1 {
+                            if config.only_delimited {
+                                continue;
+                            }
+                            vec![&record[0]]
+                        } else {
+                            let pos = select(
+                                field_pos,
+                                record.len(),
+                                config.complement,
+                            );
+                            extract_fields(&record, &pos)
+                        };
+                        match output_delimiter {
+                            Some(delim) if delim.len() != 1 => {
+                                println!("{}", join_fields(&fields, delim))
+                            }
+                            _ => wtr.write_record(fields)?,
+                        }
                     }
                 }
                 Bytes(byte_pos) => {
                     for line in file.lines() {
-                        println!("{}", extract_bytes(&line?, byte_pos));
+                        let line = line?;
+                        let pos =
+                            select(byte_pos, line.len(), config.complement);
+                        println!(
+                            "{}",
+                            join_ranges(&pos, output_delimiter, |pos| {
+                                extract_bytes(&line, pos)
+                            })
+                        );
                     }
                 }
                 Chars(char_pos) => {
                     for line in file.lines() {
-                        println!("{}", extract_chars(&line?, char_pos));
+                        let line = line?;
+                        let len = line.chars().count();
+                        let pos = select(char_pos, len, config.complement);
+                        println!(
+                            "{}",
+                            join_ranges(&pos, output_delimiter, |pos| {
+                                extract_chars(&line, pos)
+                            })
+                        );
                     }
                 }
             },
@@ -158,44 +244,141 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 // one less than the number represented by the original input.
 fn parse_index(input: &str) -> Result<usize, String> {
     let value_error = || format!("illegal list value: \"{}\"", input);
-    input
-        .starts_with('+')
-        .then(|| Err(value_error()))
-        .unwrap_or_else(|| {
-            input
-                .parse::<NonZeroUsize>()
-                .map(|n| usize::from(n) - 1)
-                .map_err(|_| value_error())
-        })
+    if input.starts_with('+') {
+        Err(value_error())
+    } else {
+        input
+            .parse::<NonZeroUsize>()
+            .map(|n| usize::from(n) - 1)
+            .map_err(|_| value_error())
+    }
 }
 
 // --------------------------------------------------
+// Parse a comma-separated list of positions, each a number N or a
+// range N-M, N- to the end of the line or -M from the start.
 fn parse_pos(range: &str) -> MyResult<PositionList> {
-    let range_re = Regex::new(r"^(\d+)-(\d+)$").unwrap();
+    let range_re = Regex::new(r"^(\d+)?-(\d+)?$").unwrap();
     range
         .split(',')
-        .into_iter()
         .map(|val| {
             parse_index(val).map(|n| n..n + 1).or_else(|e| {
-                range_re.captures(val).ok_or(e).and_then(|captures| {
-                    let n1 = parse_index(&captures[1])?;
-                    let n2 = parse_index(&captures[2])?;
-                    if n1 >= n2 {
-                        return Err(format!(
-                            "First number in range ({}) \
-                            must be lower than second number ({})",
-                            n1 + 1,
-                            n2 + 1
-                        ));
+                let captures = match range_re.captures(val) {
+                    Some(captures) => captures,
+                    None => return Err(e),
+                };
+                let index = |i| {
+                    captures
+                        .get(i)
+                        .map(|m: regex::Match| parse_index(m.as_str()))
+                        .transpose()
+                };
+                match (index(1)?, index(2)?) {
+                    (None, None) => Err(e),
+                    (Some(n1), Some(n2)) if n1 > n2 => Err(format!(
+                        "First number in range ({}) \
+                        must not be greater than second number ({})",
+                        n1 + 1,
+                        n2 + 1
+                    )),
+                    (n1, n2) => {
+                        Ok(n1.unwrap_or(0)..n2.map_or(usize::MAX, |n| n + 1))
                     }
-                    Ok(n1..n2 + 1)
-                })
+                }
             })
         })
         .collect::<Result<_, _>>()
         .map_err(From::from)
 }
 
+// --------------------------------------------------
+// Sort the positions and merge those that overlap, so each is
+// selected once and in the order of the input, as GNU cut does.
+// Ranges that only touch are kept apart, as GNU cut puts the
+// --output-delimiter between them.
+fn in_input_order(mut pos: PositionList) -> PositionList {
+    pos.sort_by_key(|range| range.start);
+    let mut merged: PositionList = vec![];
+    for range in pos {
+        match merged.last_mut() {
+            Some(last) if range.start < last.end => {
+                last.end = last.end.max(range.end)
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+// --------------------------------------------------
+// Returns the positions as given, or with --complement the positions
+// below len that are in none of them, in order.
+fn select(
+    pos: &[Range<usize>],
+    len: usize,
+    complement: bool,
+) -> Cow<'_, [Range<usize>]> {
+    if !complement {
+        return Cow::Borrowed(pos);
+    }
+    let mut ranges = vec![];
+    let mut start = 0;
+    for i in 0..len {
+        if pos.iter().any(|range| range.contains(&i)) {
+            if start < i {
+                ranges.push(start..i);
+            }
+            start = i + 1;
+        }
+    }
+    if start < len {
+        ranges.push(start..len);
+    }
+    Cow::Owned(ranges)
+}
+
+// --------------------------------------------------
+// Extracts all the positions at once, or with --output-delimiter
+// each range apart, joining those that aren't empty with it.
+fn join_ranges<F>(
+    pos: &[Range<usize>],
+    output_delimiter: Option<&str>,
+    extract: F,
+) -> String
+where
+    F: Fn(&[Range<usize>]) -> String,
+{
+    match output_delimiter {
+        None => extract(pos),
+        Some(delim) => pos
+            .iter()
+            .map(|range| extract(slice::from_ref(range)))
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(delim),
+    }
+}
+
+// --------------------------------------------------
+// Join fields with an output delimiter the csv writer can't take,
+// quoting as it would a field with the delimiter, a quote or a line
+// break in it.
+fn join_fields(fields: &[&str], delim: &str) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if (!delim.is_empty() && field.contains(delim))
+                || field.contains(['"', '\n', '\r'])
+            {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(delim)
+}
+
 // --------------------------------------------------
 fn extract_fields<'a>(
     record: &'a StringRecord,
@@ -204,7 +387,10 @@ fn extract_fields<'a>(
     field_pos
         .iter()
         .cloned()
-        .flat_map(|range| range.filter_map(|i| record.get(i)))
+        .flat_map(|range| {
+            (range.start..range.end.min(record.len()))
+                .filter_map(|i| record.get(i))
+        })
         .collect()
 }
 
@@ -214,7 +400,11 @@ fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
     let selected: Vec<_> = byte_pos
         .iter()
         .cloned()
-        .flat_map(|range| range.filter_map(|i| bytes.get(i)).copied())
+        .flat_map(|range| {
+            (range.start..range.end.min(bytes.len()))
+                .filter_map(|i| bytes.get(i))
+                .copied()
+        })
         .collect();
     String::from_utf8_lossy(&selected).into_owned()
 }
@@ -225,14 +415,22 @@ fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
     char_pos
         .iter()
         .cloned()
-        .flat_map(|range| range.filter_map(|i| chars.get(i)))
+        .flat_map(|range| {
+            (range.start..range.end.min(chars.len()))
+                .filter_map(|i| chars.get(i))
+        })
         .collect()
 }
 
 // --------------------------------------------------
 #[cfg(test)]
+// the positions are ranges even when there is just one
+#[allow(clippy::single_range_in_vec_init)]
 mod unit_tests {
-    use super::{extract_bytes, extract_chars, extract_fields, parse_pos};
+    use super::{
+        extract_bytes, extract_chars, extract_fields, in_input_order,
+        join_fields, join_ranges, parse_pos, select,
+    };
     use csv::StringRecord;
 
     #[test]
@@ -252,10 +450,7 @@ mod unit_tests {
         // A leading "+" is an error
         let res = parse_pos("+1");
         assert!(res.is_err());
-        assert_eq!(
-            res.unwrap_err().to_string(),
-            "illegal list value: \"+1\"",
-        );
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"+1\"",);
 
         let res = parse_pos("+1-2");
         assert!(res.is_err());
@@ -282,17 +477,11 @@ mod unit_tests {
 
         let res = parse_pos("1-a");
         assert!(res.is_err());
-        assert_eq!(
-            res.unwrap_err().to_string(),
-            "illegal list value: \"1-a\"",
-        );
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"1-a\"",);
 
         let res = parse_pos("a-1");
         assert!(res.is_err());
-        assert_eq!(
-            res.unwrap_err().to_string(),
-            "illegal list value: \"a-1\"",
-        );
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"a-1\"",);
 
         // Wonky ranges
         let res = parse_pos("-");
@@ -304,28 +493,27 @@ mod unit_tests {
         let res = parse_pos("1,");
         assert!(res.is_err());
 
-        let res = parse_pos("1-");
-        assert!(res.is_err());
-
         let res = parse_pos("1-1-1");
         assert!(res.is_err());
 
         let res = parse_pos("1-1-a");
         assert!(res.is_err());
 
-        // First number must be less than second
-        let res = parse_pos("1-1");
+        let res = parse_pos("-0");
         assert!(res.is_err());
-        assert_eq!(
-            res.unwrap_err().to_string(),
-            "First number in range (1) must be lower than second number (1)"
-        );
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"0\"",);
+
+        let res = parse_pos("1,-");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"-\"",);
 
+        // First number must not be greater than second
         let res = parse_pos("2-1");
         assert!(res.is_err());
         assert_eq!(
             res.unwrap_err().to_string(),
-            "First number in range (2) must be lower than second number (1)"
+            "First number in range (2) must not be greater than second \
+            number (1)"
         );
 
         // All the following are acceptable
@@ -360,6 +548,60 @@ mod unit_tests {
         let res = parse_pos("15,19-20");
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+
+        let res = parse_pos("1-1");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..1]);
+
+        // Ranges may be open at either end
+        let res = parse_pos("3-");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![2..usize::MAX]);
+
+        let res = parse_pos("-5,2");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..5, 1..2]);
+    }
+
+    #[test]
+    fn test_in_input_order() {
+        assert_eq!(in_input_order(vec![2..3, 0..1]), vec![0..1, 2..3]);
+        assert_eq!(in_input_order(vec![0..1, 0..1]), vec![0..1]);
+        assert_eq!(in_input_order(vec![1..4, 0..3]), vec![0..4]);
+        assert_eq!(
+            in_input_order(vec![5..usize::MAX, 0..2, 4..6]),
+            vec![0..2, 4..usize::MAX]
+        );
+        // Ranges that only touch stay apart
+        assert_eq!(in_input_order(vec![2..4, 0..2]), vec![0..2, 2..4]);
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(*select(&[1..2], 4, false), [1..2]);
+        assert_eq!(*select(&[1..2], 4, true), [0..1, 2..4]);
+        assert_eq!(*select(&[3..5, 0..1], 6, true), [1..3, 5..6]);
+        assert!(select(&[0..2, 1..3], 3, true).is_empty());
+        assert_eq!(*select(&[7..8], 2, true), [0..2]);
+    }
+
+    #[test]
+    fn test_join_ranges() {
+        let extract = |pos: &[_]| extract_chars("ábcde", pos);
+        assert_eq!(join_ranges(&[0..2, 3..4], None, extract), "ábd");
+        assert_eq!(join_ranges(&[0..2, 3..4], Some(":"), extract), "áb:d");
+        assert_eq!(join_ranges(&[5..6, 0..1], Some("::"), extract), "á");
+    }
+
+    #[test]
+    fn test_join_fields() {
+        assert_eq!(join_fields(&["a", "b"], "::"), "a::b");
+        assert_eq!(join_fields(&["a::b", "c"], "::"), "\"a::b\"::c");
+        assert_eq!(
+            join_fields(&["say \"hi\"", "c"], ""),
+            "\"say \"\"hi\"\"\"c"
+        );
+        assert_eq!(join_fields(&["a,b", "c"], "::"), "a,b::c");
     }
 
     #[test]
@@ -367,12 +609,10 @@ mod unit_tests {
         let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
         assert_eq!(extract_fields(&rec, &[0..1]), &["Captain"]);
         assert_eq!(extract_fields(&rec, &[1..2]), &["Sham"]);
-        assert_eq!(
-            extract_fields(&rec, &[0..1, 2..3]),
-            &["Captain", "12345"]
-        );
+        assert_eq!(extract_fields(&rec, &[0..1, 2..3]), &["Captain", "12345"]);
         assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &["Captain"]);
         assert_eq!(extract_fields(&rec, &[1..2, 0..1]), &["Sham", "Captain"]);
+        assert_eq!(extract_fields(&rec, &[1..usize::MAX]), &["Sham", "12345"]);
     }
 
     #[test]
@@ -382,10 +622,8 @@ mod unit_tests {
         assert_eq!(extract_chars("ábc", &[0..1, 2..3]), "ác".to_string());
         assert_eq!(extract_chars("ábc", &[0..3]), "ábc".to_string());
         assert_eq!(extract_chars("ábc", &[2..3, 1..2]), "cb".to_string());
-        assert_eq!(
-            extract_chars("ábc", &[0..1, 1..2, 4..5]),
-            "áb".to_string()
-        );
+        assert_eq!(extract_chars("ábc", &[1..usize::MAX]), "bc".to_string());
+        assert_eq!(extract_chars("ábc", &[0..1, 1..2, 4..5]), "áb".to_string());
     }
 
     #[test]
@@ -395,6 +633,7 @@ mod unit_tests {
         assert_eq!(extract_bytes("ábc", &[0..3]), "áb".to_string());
         assert_eq!(extract_bytes("ábc", &[0..4]), "ábc".to_string());
         assert_eq!(extract_bytes("ábc", &[3..4, 2..3]), "cb".to_string());
+        assert_eq!(extract_bytes("ábc", &[2..usize::MAX]), "bc".to_string());
         assert_eq!(extract_bytes("ábc", &[0..2, 5..6]), "á".to_string());
     }
 }
@@ -9,6 +9,8 @@ const PRG: &str = "cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
 const TSV: &str = "tests/inputs/movies1.tsv";
 const BOOKS: &str = "tests/inputs/books.tsv";
+const MIXED: &str = "tests/inputs/mixed.tsv";
+const QUOTED: &str = "tests/inputs/quoted.csv";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -35,7 +37,7 @@ fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&["-f", "1", CSV, &bad, TSV])
+        .args(["-f", "1", CSV, &bad, TSV])
         .assert()
         .success()
         .stderr(predicate::str::is_match(expected)?);
@@ -110,7 +112,7 @@ fn dies_bad_delimiter() -> TestResult {
 #[test]
 fn dies_chars_bytes_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-f", "1", "-b", "1"])
+        .args([CSV, "-c", "1", "-f", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -120,7 +122,7 @@ fn dies_chars_bytes_fields() -> TestResult {
 #[test]
 fn dies_bytes_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-f", "1", "-b", "1"])
+        .args([CSV, "-f", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -130,7 +132,7 @@ fn dies_bytes_fields() -> TestResult {
 #[test]
 fn dies_chars_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-f", "1"])
+        .args([CSV, "-c", "1", "-f", "1"])
         .assert()
         .failure();
     Ok(())
@@ -140,7 +142,7 @@ fn dies_chars_fields() -> TestResult {
 #[test]
 fn dies_chars_bytes() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-b", "1"])
+        .args([CSV, "-c", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -337,3 +339,137 @@ fn tsv_c1_8() -> TestResult {
 fn repeated_value() -> TestResult {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_complement() -> TestResult {
+    run(
+        &[TSV, "-f", "2", "--complement"],
+        "tests/expected/movies1.tsv.f2.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_c1_2_4_5_complement() -> TestResult {
+    run(
+        &[TSV, "-c", "1-2,4-5", "--complement"],
+        "tests/expected/movies1.tsv.c1-2,4-5.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f1_3_output_delim() -> TestResult {
+    run(
+        &[CSV, "-f", "1,3", "-d", ",", "--output-delimiter", " | "],
+        "tests/expected/movies1.csv.f1,3.output-delim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_c1_2_4_5_output_delim() -> TestResult {
+    run(
+        &[TSV, "-c", "1-2,4-5", "--output-delimiter", ":"],
+        "tests/expected/movies1.tsv.c1-2,4-5.output-delim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_f2() -> TestResult {
+    run(&[MIXED, "-f", "2"], "tests/expected/mixed.tsv.f2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_f2_only_delimited() -> TestResult {
+    run(
+        &[MIXED, "-f", "2", "-s"],
+        "tests/expected/mixed.tsv.f2.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_only_delimited_chars() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([MIXED, "-c", "1", "-s"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_to_end() -> TestResult {
+    run(&[TSV, "-f", "2-"], "tests/expected/movies1.tsv.f2-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f_to_2() -> TestResult {
+    run(
+        &[CSV, "-f", "-2", "-d", ","],
+        "tests/expected/movies1.csv.f-2.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_b4_to_end_to_2_input_order() -> TestResult {
+    run(
+        &[
+            TSV,
+            "-b",
+            "4-,-2",
+            "--output-delimiter",
+            ":",
+            "--input-order",
+        ],
+        "tests/expected/movies1.tsv.b4-,-2.output-delim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f3_1_1_input_order() -> TestResult {
+    run(
+        &[TSV, "-f", "3,1,1-1", "--input-order"],
+        "tests/expected/movies1.tsv.f3,1,1-1.input-order.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_decreasing_range() -> TestResult {
+    dies(
+        &[TSV, "-f", "3-2"],
+        "First number in range (3) must not be greater than second number (2)",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn quoted_output_delim() -> TestResult {
+    // Fields are quoted again as needed for the output delimiter
+    for (delim, expected) in [
+        (
+            ",",
+            "name,note\n\"Smith, John\",ok\n\"say \"\"hi\"\"\",fine\n",
+        ),
+        (";", "name;note\nSmith, John;ok\n\"say \"\"hi\"\"\";fine\n"),
+        (
+            ", ",
+            "name, note\n\"Smith, John\", ok\n\"say \"\"hi\"\"\", fine\n",
+        ),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args([QUOTED, "-d", ",", "-f", "2,3", "--output-delimiter", delim])
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+    Ok(())
+}